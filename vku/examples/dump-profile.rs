@@ -0,0 +1,166 @@
+//! Prints the Vulkan devices of this machine as a profile for `tests/fixtures`
+//!
+//! ```sh
+//! cargo run -p vku --example dump-profile --features rwh_06 > vku/tests/fixtures/name.json
+//! ```
+//!
+//! The surface is queried for a window of 1280x720, the size the snapshot tests
+//! expect. Add the name of the profile to `PROFILES` in `src/backend.rs`, fill its
+//! description and write its snapshot running the tests with `VKU_UPDATE_SNAPSHOTS=1`.
+
+use std::ffi::CStr;
+
+use ash::{extensions::khr, vk};
+use serde_json::{json, Value};
+
+/// Returns a field of the features
+type Feature = fn(&vk::PhysicalDeviceFeatures) -> vk::Bool32;
+
+/// The features recorded in the profiles, the same read by the tests
+const FEATURES: [(&str, Feature); 12] = [
+    ("robust_buffer_access", |f| f.robust_buffer_access),
+    ("geometry_shader", |f| f.geometry_shader),
+    ("tessellation_shader", |f| f.tessellation_shader),
+    ("sample_rate_shading", |f| f.sample_rate_shading),
+    ("multi_draw_indirect", |f| f.multi_draw_indirect),
+    ("fill_mode_non_solid", |f| f.fill_mode_non_solid),
+    ("wide_lines", |f| f.wide_lines),
+    ("sampler_anisotropy", |f| f.sampler_anisotropy),
+    ("texture_compression_etc2", |f| f.texture_compression_etc2),
+    ("texture_compression_astc_ldr", |f| {
+        f.texture_compression_astc_ldr
+    }),
+    ("texture_compression_bc", |f| f.texture_compression_bc),
+    ("shader_float64", |f| f.shader_float64),
+];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let event_loop = winit::event_loop::EventLoop::new()?;
+    let window = winit::window::WindowBuilder::new()
+        .with_title("dump-profile")
+        .with_inner_size(winit::dpi::PhysicalSize::new(1280, 720))
+        .with_resizable(false)
+        .build(&event_loop)?;
+
+    let entry = unsafe { ash::Entry::load()? };
+    let mut extensions = vku::ExtensionList::from([khr::Surface::name()]);
+    extensions.extend(vku::window_handle::extensions_checked(&window, &entry)?.iter());
+    let no_layers: [&CStr; 0] = [];
+    let instance = vku::Instance::new(&entry, no_layers, extensions, c"dump-profile")?;
+    let surface = vku::Surface::from_window(instance, &window)?;
+    let list = vku::PhysicalDevList::list(surface)?;
+
+    let devices = list.iter().map(device).collect::<vku::Result<Vec<_>>>()?;
+    let profile = json!({
+        "description": "",
+        "devices": devices,
+    });
+    println!("{}", serde_json::to_string_pretty(&profile)?);
+    Ok(())
+}
+
+/// Describes a device in the format of the fixtures
+fn device<I: vku::SurfaceHolder>(dev: vku::PhysicalDevRef<'_, I>) -> vku::Result<Value> {
+    let props = dev.properties();
+    let features = dev.features();
+    let memory = dev.memory_properties();
+    let families = dev.queue_families();
+
+    let mut present = vec![];
+    for family in 0..families.len() as u32 {
+        // SAFETY: The index is in the range of the queue families
+        present.push(unsafe { dev.supports_surface(family)? });
+    }
+    let surface = match present.contains(&true) {
+        // SAFETY: At least one queue family supports the surface
+        true => surface(&unsafe { dev.surface_info()? }),
+        false => Value::Null,
+    };
+
+    let extensions: Vec<_> = dev
+        .extension_properties()?
+        .iter()
+        // SAFETY: The name is a null terminated string written by the Vulkan driver
+        .map(|ext| unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) }.to_string_lossy())
+        .collect();
+    let queue_families: Vec<_> = families
+        .iter()
+        .zip(present)
+        .map(|(fam, present)| {
+            let granularity = fam.min_image_transfer_granularity;
+            json!({
+                "flags": format!("{:?}", fam.queue_flags),
+                "count": fam.queue_count,
+                "timestamp_valid_bits": fam.timestamp_valid_bits,
+                "min_image_transfer_granularity":
+                    [granularity.width, granularity.height, granularity.depth],
+                "present": present,
+            })
+        })
+        .collect();
+    let heaps = &memory.memory_heaps[..memory.memory_heap_count as usize];
+    let types = &memory.memory_types[..memory.memory_type_count as usize];
+    let version = props.api_version;
+
+    Ok(json!({
+        // SAFETY: The name is a null terminated string written by the Vulkan driver
+        "name": unsafe { CStr::from_ptr(props.device_name.as_ptr()) }.to_string_lossy(),
+        "device_type": format!("{:?}", props.device_type),
+        "api_version": format!(
+            "{}.{}.{}",
+            vk::api_version_major(version),
+            vk::api_version_minor(version),
+            vk::api_version_patch(version),
+        ),
+        "driver_version": props.driver_version,
+        "vendor_id": props.vendor_id,
+        "device_id": props.device_id,
+        "features": FEATURES
+            .iter()
+            .filter(|(_, enabled)| enabled(&features) == vk::TRUE)
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+        "extensions": extensions,
+        "queue_families": queue_families,
+        "memory_heaps": heaps
+            .iter()
+            .map(|heap| json!({ "size": heap.size, "flags": format!("{:?}", heap.flags) }))
+            .collect::<Vec<_>>(),
+        "memory_types": types
+            .iter()
+            .map(|ty| json!({
+                "heap": ty.heap_index,
+                "flags": format!("{:?}", ty.property_flags),
+            }))
+            .collect::<Vec<_>>(),
+        "surface": surface,
+    }))
+}
+
+/// Describes the surface capabilities, formats and present modes of a device
+fn surface(info: &vku::surface::SurfaceInfo) -> Value {
+    let caps = &info.capabilities;
+    let extent = |ext: vk::Extent2D| [ext.width, ext.height];
+    json!({
+        "min_image_count": caps.min_image_count,
+        "max_image_count": caps.max_image_count,
+        "current_extent": extent(caps.current_extent),
+        "min_image_extent": extent(caps.min_image_extent),
+        "max_image_extent": extent(caps.max_image_extent),
+        "max_image_array_layers": caps.max_image_array_layers,
+        "supported_transforms": format!("{:?}", caps.supported_transforms),
+        "current_transform": format!("{:?}", caps.current_transform),
+        "supported_composite_alpha": format!("{:?}", caps.supported_composite_alpha),
+        "supported_usage_flags": format!("{:?}", caps.supported_usage_flags),
+        "formats": info
+            .formats
+            .iter()
+            .map(|fmt| [format!("{:?}", fmt.format), format!("{:?}", fmt.color_space)])
+            .collect::<Vec<_>>(),
+        "present_modes": info
+            .present_modes
+            .iter()
+            .map(|mode| format!("{mode:?}"))
+            .collect::<Vec<_>>(),
+    })
+}
@@ -0,0 +1,446 @@
+use std::{
+    alloc::{self, Layout},
+    os::raw::c_void,
+    ptr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use ash::vk;
+
+/// A host memory allocator used by the Vulkan implementation for its internal allocations
+///
+/// The allocator is set when creating the [`vku::Instance`](super::Instance) and
+/// is then used by every object created through it.
+///
+/// # Safety
+///
+/// The implementation must follow the rules described in the Vulkan specification for
+/// [`vk::AllocationCallbacks`], in particular:
+/// - the memory returned by `allocate` and `reallocate` must be aligned to `alignment`
+/// - `reallocate` must preserve the contents of the original allocation
+/// - the methods must never panic, as they are called through the FFI boundary
+pub unsafe trait HostAllocator: Send + Sync {
+    /// Allocates `size` bytes aligned to `alignment`,
+    /// returns null if the allocation fails
+    fn allocate(
+        &self,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void;
+
+    /// Resizes the `original` allocation to `size` bytes aligned to `alignment`
+    ///
+    /// If `original` is null it behaves like `allocate`,
+    /// if `size` is zero it behaves like `free` and returns null.
+    ///
+    /// # Safety
+    ///
+    /// `original` must be null or a pointer returned by this allocator that was not freed
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void;
+
+    /// Frees the memory returned by a previous call to `allocate` or `reallocate`
+    ///
+    /// # Safety
+    ///
+    /// `memory` must be null or a pointer returned by this allocator that was not freed
+    unsafe fn free(&self, memory: *mut c_void);
+
+    /// Notifies that the implementation allocated `size` bytes on its own
+    fn internal_allocation(
+        &self,
+        _size: usize,
+        _ty: vk::InternalAllocationType,
+        _scope: vk::SystemAllocationScope,
+    ) {
+    }
+
+    /// Notifies that the implementation freed `size` bytes allocated on its own
+    fn internal_free(
+        &self,
+        _size: usize,
+        _ty: vk::InternalAllocationType,
+        _scope: vk::SystemAllocationScope,
+    ) {
+    }
+}
+
+/// Returns the [`HostAllocator`] stored in the user data of the callbacks
+///
+/// # Safety
+///
+/// `user_data` must be the pointer created by [`Callbacks::new`]
+unsafe fn allocator<'a>(user_data: *mut c_void) -> &'a dyn HostAllocator {
+    &**(user_data as *const Arc<dyn HostAllocator>)
+}
+
+unsafe extern "system" fn pfn_allocation(
+    user_data: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    allocator(user_data).allocate(size, alignment, scope)
+}
+
+unsafe extern "system" fn pfn_reallocation(
+    user_data: *mut c_void,
+    original: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    allocator(user_data).reallocate(original, size, alignment, scope)
+}
+
+unsafe extern "system" fn pfn_free(user_data: *mut c_void, memory: *mut c_void) {
+    allocator(user_data).free(memory)
+}
+
+unsafe extern "system" fn pfn_internal_allocation(
+    user_data: *mut c_void,
+    size: usize,
+    ty: vk::InternalAllocationType,
+    scope: vk::SystemAllocationScope,
+) {
+    allocator(user_data).internal_allocation(size, ty, scope)
+}
+
+unsafe extern "system" fn pfn_internal_free(
+    user_data: *mut c_void,
+    size: usize,
+    ty: vk::InternalAllocationType,
+    scope: vk::SystemAllocationScope,
+) {
+    allocator(user_data).internal_free(size, ty, scope)
+}
+
+/// The [`vk::AllocationCallbacks`] that forward the calls to a [`HostAllocator`]
+pub(super) struct Callbacks {
+    /// The allocator pointed by the user data of the callbacks,
+    /// the box makes its address stable
+    _allocator: Box<Arc<dyn HostAllocator>>,
+    /// The actual Vulkan callbacks
+    callbacks: vk::AllocationCallbacks,
+}
+
+// SAFETY: the raw pointer in the callbacks points to the allocator
+// which is required to be both Send and Sync
+unsafe impl Send for Callbacks {}
+unsafe impl Sync for Callbacks {}
+
+impl Callbacks {
+    pub fn new(allocator: Arc<dyn HostAllocator>) -> Self {
+        let allocator = Box::new(allocator);
+        let callbacks = vk::AllocationCallbacks {
+            p_user_data: &*allocator as *const Arc<dyn HostAllocator> as *mut c_void,
+            pfn_allocation: Some(pfn_allocation),
+            pfn_reallocation: Some(pfn_reallocation),
+            pfn_free: Some(pfn_free),
+            pfn_internal_allocation: Some(pfn_internal_allocation),
+            pfn_internal_free: Some(pfn_internal_free),
+        };
+        Self {
+            _allocator: allocator,
+            callbacks,
+        }
+    }
+
+    pub fn vk(&self) -> &vk::AllocationCallbacks {
+        &self.callbacks
+    }
+}
+
+/// Number of [`vk::SystemAllocationScope`] values
+const SCOPES: usize = 5;
+
+/// Number of counters kept by the [`CountingAllocator`]: one for each scope
+/// and a last one for the scopes unknown when this was written
+const BUCKETS: usize = SCOPES + 1;
+
+/// Returns the counter for the scope with the raw value `scope`,
+/// unknown scopes share the last bucket
+fn counter(counters: &[AtomicUsize; BUCKETS], scope: usize) -> &AtomicUsize {
+    counters.get(scope).unwrap_or(&counters[SCOPES])
+}
+
+/// Allocation totals reported by the [`CountingAllocator`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Number of bytes currently allocated
+    pub bytes: usize,
+    /// Number of allocations currently alive
+    pub count: usize,
+}
+
+/// A [`HostAllocator`] that uses the Rust global allocator
+/// and keeps track of the live allocations for each [`vk::SystemAllocationScope`]
+///
+/// Useful to check that every object created has been destroyed
+/// by verifying that the [`total`](Self::total) goes back to zero.
+///
+/// Allocations with a scope not defined by the Vulkan headers this crate is built with
+/// are counted together, they are only included in the [`total`](Self::total).
+#[derive(Default)]
+pub struct CountingAllocator {
+    bytes: [AtomicUsize; BUCKETS],
+    count: [AtomicUsize; BUCKETS],
+    internal_bytes: [AtomicUsize; BUCKETS],
+}
+
+impl CountingAllocator {
+    /// Creates a new allocator with no live allocations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the live allocations made for the provided scope
+    pub fn stats(&self, scope: vk::SystemAllocationScope) -> AllocationStats {
+        let i = scope.as_raw() as usize;
+        AllocationStats {
+            bytes: counter(&self.bytes, i).load(Ordering::Relaxed),
+            count: counter(&self.count, i).load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the live allocations made for all the scopes
+    pub fn total(&self) -> AllocationStats {
+        (0..BUCKETS).fold(AllocationStats::default(), |acc, i| AllocationStats {
+            bytes: acc.bytes + self.bytes[i].load(Ordering::Relaxed),
+            count: acc.count + self.count[i].load(Ordering::Relaxed),
+        })
+    }
+
+    /// Returns the bytes that the implementation reported to have allocated on its own
+    pub fn internal_bytes(&self, scope: vk::SystemAllocationScope) -> usize {
+        counter(&self.internal_bytes, scope.as_raw() as usize).load(Ordering::Relaxed)
+    }
+
+    /// Size of the header stored before each allocation, which contains
+    /// the requested size, alignment and scope of the allocation
+    fn header_size(alignment: usize) -> usize {
+        alignment.max((3 * std::mem::size_of::<usize>()).next_power_of_two())
+    }
+
+    /// Returns the layout of the whole allocation, header included
+    fn layout(size: usize, alignment: usize) -> Option<Layout> {
+        let header = Self::header_size(alignment);
+        let align = alignment.max(std::mem::align_of::<usize>());
+        Layout::from_size_align(header.checked_add(size)?, align).ok()
+    }
+
+    /// Reads the header of the allocation
+    ///
+    /// # Safety
+    ///
+    /// `memory` must have been returned by [`HostAllocator::allocate`]
+    unsafe fn header(memory: *mut c_void) -> [usize; 3] {
+        (memory as *const [usize; 3]).sub(1).read()
+    }
+}
+
+unsafe impl HostAllocator for CountingAllocator {
+    fn allocate(
+        &self,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void {
+        let layout = match Self::layout(size, alignment) {
+            Some(layout) if size != 0 => layout,
+            _ => return ptr::null_mut(),
+        };
+        unsafe {
+            let base = alloc::alloc(layout);
+            if base.is_null() {
+                return ptr::null_mut();
+            }
+            let memory = base.add(Self::header_size(alignment)) as *mut c_void;
+            let header = [size, alignment, scope.as_raw() as usize];
+            (memory as *mut [usize; 3]).sub(1).write(header);
+
+            let i = scope.as_raw() as usize;
+            counter(&self.bytes, i).fetch_add(size, Ordering::Relaxed);
+            counter(&self.count, i).fetch_add(1, Ordering::Relaxed);
+            memory
+        }
+    }
+
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void {
+        if original.is_null() {
+            return self.allocate(size, alignment, scope);
+        }
+        if size == 0 {
+            self.free(original);
+            return ptr::null_mut();
+        }
+        let memory = self.allocate(size, alignment, scope);
+        if !memory.is_null() {
+            let [old_size, ..] = Self::header(original);
+            let len = old_size.min(size);
+            ptr::copy_nonoverlapping(original as *const u8, memory as *mut u8, len);
+            self.free(original);
+        }
+        memory
+    }
+
+    unsafe fn free(&self, memory: *mut c_void) {
+        if memory.is_null() {
+            return;
+        }
+        let [size, alignment, scope] = Self::header(memory);
+        let header = Self::header_size(alignment);
+        // The layout was valid when the memory was allocated
+        let layout = Self::layout(size, alignment).unwrap_unchecked();
+        alloc::dealloc((memory as *mut u8).sub(header), layout);
+
+        counter(&self.bytes, scope).fetch_sub(size, Ordering::Relaxed);
+        counter(&self.count, scope).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn internal_allocation(
+        &self,
+        size: usize,
+        _ty: vk::InternalAllocationType,
+        scope: vk::SystemAllocationScope,
+    ) {
+        counter(&self.internal_bytes, scope.as_raw() as usize).fetch_add(size, Ordering::Relaxed);
+    }
+
+    fn internal_free(
+        &self,
+        size: usize,
+        _ty: vk::InternalAllocationType,
+        scope: vk::SystemAllocationScope,
+    ) {
+        counter(&self.internal_bytes, scope.as_raw() as usize).fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vk::SystemAllocationScope as Scope;
+
+    fn stats(bytes: usize, count: usize) -> AllocationStats {
+        AllocationStats { bytes, count }
+    }
+
+    #[test]
+    fn counts_each_scope() {
+        let allocator = CountingAllocator::new();
+        let a = allocator.allocate(64, 8, Scope::OBJECT);
+        let b = allocator.allocate(32, 16, Scope::OBJECT);
+        let c = allocator.allocate(100, 64, Scope::DEVICE);
+        assert_eq!(allocator.stats(Scope::OBJECT), stats(96, 2));
+        assert_eq!(allocator.stats(Scope::DEVICE), stats(100, 1));
+        assert_eq!(allocator.stats(Scope::COMMAND), stats(0, 0));
+        assert_eq!(allocator.total(), stats(196, 3));
+
+        unsafe {
+            allocator.free(a);
+            allocator.free(c);
+        }
+        assert_eq!(allocator.stats(Scope::OBJECT), stats(32, 1));
+        assert_eq!(allocator.stats(Scope::DEVICE), stats(0, 0));
+        unsafe { allocator.free(b) };
+        assert_eq!(allocator.total(), AllocationStats::default());
+    }
+
+    #[test]
+    fn respects_alignment() {
+        let allocator = CountingAllocator::new();
+        for alignment in [1, 4, 16, 64, 256, 4096] {
+            let memory = allocator.allocate(3, alignment, Scope::CACHE);
+            assert!(!memory.is_null());
+            assert_eq!(memory as usize % alignment, 0);
+            unsafe { allocator.free(memory) };
+        }
+        assert_eq!(allocator.total(), AllocationStats::default());
+    }
+
+    #[test]
+    fn reallocation_moves_the_accounting() {
+        let allocator = CountingAllocator::new();
+        unsafe {
+            let memory = allocator.reallocate(ptr::null_mut(), 4, 4, Scope::COMMAND);
+            (memory as *mut u32).write(0xdead_beef);
+            assert_eq!(allocator.stats(Scope::COMMAND), stats(4, 1));
+
+            let memory = allocator.reallocate(memory, 16, 8, Scope::OBJECT);
+            assert_eq!((memory as *const u32).read(), 0xdead_beef);
+            assert_eq!(allocator.stats(Scope::COMMAND), stats(0, 0));
+            assert_eq!(allocator.stats(Scope::OBJECT), stats(16, 1));
+
+            let memory = allocator.reallocate(memory, 0, 8, Scope::OBJECT);
+            assert!(memory.is_null());
+        }
+        assert_eq!(allocator.total(), AllocationStats::default());
+    }
+
+    #[test]
+    fn zero_sized_allocations_fail() {
+        let allocator = CountingAllocator::new();
+        assert!(allocator.allocate(0, 8, Scope::OBJECT).is_null());
+        unsafe { allocator.free(ptr::null_mut()) };
+        assert_eq!(allocator.total(), AllocationStats::default());
+    }
+
+    #[test]
+    fn unknown_scopes_are_counted_together() {
+        let allocator = CountingAllocator::new();
+        let unknown = [
+            Scope::from_raw(5),
+            Scope::from_raw(1000),
+            Scope::from_raw(-1),
+        ];
+        let memory: Vec<_> = unknown
+            .iter()
+            .map(|&scope| allocator.allocate(10, 8, scope))
+            .collect();
+        assert_eq!(allocator.stats(unknown[0]), stats(30, 3));
+        assert_eq!(allocator.stats(unknown[2]), stats(30, 3));
+        assert_eq!(allocator.total(), stats(30, 3));
+        for scope in [Scope::COMMAND, Scope::OBJECT, Scope::INSTANCE] {
+            assert_eq!(allocator.stats(scope), stats(0, 0));
+        }
+
+        memory
+            .into_iter()
+            .for_each(|m| unsafe { allocator.free(m) });
+        assert_eq!(allocator.total(), AllocationStats::default());
+    }
+
+    #[test]
+    fn internal_allocations() {
+        let allocator = CountingAllocator::new();
+        let ty = vk::InternalAllocationType::EXECUTABLE;
+        allocator.internal_allocation(128, ty, Scope::DEVICE);
+        allocator.internal_allocation(7, ty, Scope::from_raw(42));
+        assert_eq!(allocator.internal_bytes(Scope::DEVICE), 128);
+        assert_eq!(allocator.internal_bytes(Scope::from_raw(42)), 7);
+        // They are not part of the allocations made through the allocator
+        assert_eq!(allocator.total(), AllocationStats::default());
+
+        allocator.internal_free(128, ty, Scope::DEVICE);
+        allocator.internal_free(7, ty, Scope::from_raw(42));
+        assert_eq!(allocator.internal_bytes(Scope::DEVICE), 0);
+        assert_eq!(allocator.internal_bytes(Scope::from_raw(42)), 0);
+    }
+}
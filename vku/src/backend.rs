@@ -0,0 +1,875 @@
+//! The Vulkan calls the crate makes to query the physical devices and the surface
+//! and to create the logical device
+//!
+//! The holders route those calls through the traits in this module, implemented by
+//! [`ash::Instance`] and [`khr::Surface`], so that the policies built on top of them
+//! can run against fixture data in the tests instead of a driver.
+
+use ash::{extensions::khr, prelude::VkResult, vk};
+
+/// The instance level calls, along with the device calls that need to
+/// be matched with them (the device is created, queried and destroyed through here)
+pub trait Backend {
+    /// Returns the handle of the instance, used to report the errors
+    fn handle(&self) -> vk::Instance;
+
+    /// `vkEnumeratePhysicalDevices`
+    unsafe fn enumerate_physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>>;
+
+    /// `vkGetPhysicalDeviceProperties`
+    unsafe fn get_physical_device_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceProperties;
+
+    /// `vkGetPhysicalDeviceFeatures`
+    unsafe fn get_physical_device_features(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceFeatures;
+
+    /// `vkGetPhysicalDeviceQueueFamilyProperties`
+    unsafe fn get_physical_device_queue_family_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> Vec<vk::QueueFamilyProperties>;
+
+    /// `vkGetPhysicalDeviceMemoryProperties`
+    unsafe fn get_physical_device_memory_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceMemoryProperties;
+
+    /// `vkEnumerateDeviceExtensionProperties`
+    unsafe fn enumerate_device_extension_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> VkResult<Vec<vk::ExtensionProperties>>;
+
+    /// `vkCreateDevice`
+    unsafe fn create_device(
+        &self,
+        device: vk::PhysicalDevice,
+        create_info: &vk::DeviceCreateInfo,
+        allocator: Option<&vk::AllocationCallbacks>,
+    ) -> VkResult<ash::Device>;
+
+    /// `vkGetDeviceQueue`
+    unsafe fn get_device_queue(
+        &self,
+        device: &ash::Device,
+        queue_family_index: u32,
+        queue_index: u32,
+    ) -> vk::Queue;
+
+    /// `vkDestroyDevice`
+    unsafe fn destroy_device(
+        &self,
+        device: &ash::Device,
+        allocator: Option<&vk::AllocationCallbacks>,
+    );
+}
+
+/// The queries about a surface
+pub trait SurfaceBackend {
+    /// `vkGetPhysicalDeviceSurfaceSupportKHR`
+    unsafe fn get_physical_device_surface_support(
+        &self,
+        device: vk::PhysicalDevice,
+        queue_family_index: u32,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<bool>;
+
+    /// `vkGetPhysicalDeviceSurfaceCapabilitiesKHR`
+    unsafe fn get_physical_device_surface_capabilities(
+        &self,
+        device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<vk::SurfaceCapabilitiesKHR>;
+
+    /// `vkGetPhysicalDeviceSurfaceFormatsKHR`
+    unsafe fn get_physical_device_surface_formats(
+        &self,
+        device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<Vec<vk::SurfaceFormatKHR>>;
+
+    /// `vkGetPhysicalDeviceSurfacePresentModesKHR`
+    unsafe fn get_physical_device_surface_present_modes(
+        &self,
+        device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<Vec<vk::PresentModeKHR>>;
+}
+
+impl Backend for ash::Instance {
+    fn handle(&self) -> vk::Instance {
+        ash::Instance::handle(self)
+    }
+
+    unsafe fn enumerate_physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>> {
+        ash::Instance::enumerate_physical_devices(self)
+    }
+
+    unsafe fn get_physical_device_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceProperties {
+        ash::Instance::get_physical_device_properties(self, device)
+    }
+
+    unsafe fn get_physical_device_features(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceFeatures {
+        ash::Instance::get_physical_device_features(self, device)
+    }
+
+    unsafe fn get_physical_device_queue_family_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> Vec<vk::QueueFamilyProperties> {
+        ash::Instance::get_physical_device_queue_family_properties(self, device)
+    }
+
+    unsafe fn get_physical_device_memory_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceMemoryProperties {
+        ash::Instance::get_physical_device_memory_properties(self, device)
+    }
+
+    unsafe fn enumerate_device_extension_properties(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> VkResult<Vec<vk::ExtensionProperties>> {
+        ash::Instance::enumerate_device_extension_properties(self, device)
+    }
+
+    unsafe fn create_device(
+        &self,
+        device: vk::PhysicalDevice,
+        create_info: &vk::DeviceCreateInfo,
+        allocator: Option<&vk::AllocationCallbacks>,
+    ) -> VkResult<ash::Device> {
+        ash::Instance::create_device(self, device, create_info, allocator)
+    }
+
+    unsafe fn get_device_queue(
+        &self,
+        device: &ash::Device,
+        queue_family_index: u32,
+        queue_index: u32,
+    ) -> vk::Queue {
+        device.get_device_queue(queue_family_index, queue_index)
+    }
+
+    unsafe fn destroy_device(
+        &self,
+        device: &ash::Device,
+        allocator: Option<&vk::AllocationCallbacks>,
+    ) {
+        device.destroy_device(allocator)
+    }
+}
+
+impl SurfaceBackend for khr::Surface {
+    unsafe fn get_physical_device_surface_support(
+        &self,
+        device: vk::PhysicalDevice,
+        queue_family_index: u32,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<bool> {
+        khr::Surface::get_physical_device_surface_support(self, device, queue_family_index, surface)
+    }
+
+    unsafe fn get_physical_device_surface_capabilities(
+        &self,
+        device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<vk::SurfaceCapabilitiesKHR> {
+        khr::Surface::get_physical_device_surface_capabilities(self, device, surface)
+    }
+
+    unsafe fn get_physical_device_surface_formats(
+        &self,
+        device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<Vec<vk::SurfaceFormatKHR>> {
+        khr::Surface::get_physical_device_surface_formats(self, device, surface)
+    }
+
+    unsafe fn get_physical_device_surface_present_modes(
+        &self,
+        device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> VkResult<Vec<vk::PresentModeKHR>> {
+        khr::Surface::get_physical_device_surface_present_modes(self, device, surface)
+    }
+}
+
+/// An instance, and optionally a surface, whose physical devices are described by fixture data
+///
+/// The `ash` function tables it hands out are never loaded, so calling through them
+/// instead of the backend panics.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::{
+        cell::Cell,
+        ffi::{c_char, CStr},
+        rc::Rc,
+    };
+
+    use cstr::cstr;
+
+    use super::*;
+
+    /// A physical device reported by the [`Instance`]
+    #[derive(Clone, Debug, Default)]
+    pub struct Device {
+        pub properties: vk::PhysicalDeviceProperties,
+        pub features: vk::PhysicalDeviceFeatures,
+        pub queue_families: Vec<vk::QueueFamilyProperties>,
+        pub memory: vk::PhysicalDeviceMemoryProperties,
+        pub extensions: Vec<vk::ExtensionProperties>,
+        /// Whether or not each queue family can present to the surface,
+        /// the families past the end can't
+        pub present_families: Vec<bool>,
+        pub surface_capabilities: vk::SurfaceCapabilitiesKHR,
+        pub surface_formats: Vec<vk::SurfaceFormatKHR>,
+        pub present_modes: Vec<vk::PresentModeKHR>,
+        /// Returned by the extension enumeration instead of the extensions
+        pub extensions_error: Option<vk::Result>,
+        /// Returned by the surface support queries instead of the support
+        pub present_error: Option<vk::Result>,
+    }
+
+    impl Device {
+        /// A device of type `device_type` named `name`,
+        /// with a single queue family that supports graphics, compute and transfer
+        /// and a device local heap of `vram` bytes
+        pub fn new(name: &str, device_type: vk::PhysicalDeviceType, vram: u64) -> Self {
+            let mut properties = vk::PhysicalDeviceProperties {
+                api_version: vk::API_VERSION_1_3,
+                device_type,
+                ..Default::default()
+            };
+            copy_name(&mut properties.device_name, name);
+            let mut memory = vk::PhysicalDeviceMemoryProperties {
+                memory_heap_count: 1,
+                ..Default::default()
+            };
+            memory.memory_heaps[0] = vk::MemoryHeap {
+                size: vram,
+                flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+            };
+            Self {
+                properties,
+                queue_families: vec![vk::QueueFamilyProperties {
+                    queue_flags: vk::QueueFlags::GRAPHICS
+                        | vk::QueueFlags::COMPUTE
+                        | vk::QueueFlags::TRANSFER,
+                    queue_count: 1,
+                    ..Default::default()
+                }],
+                memory,
+                ..Default::default()
+            }
+        }
+
+        /// Adds the device extension `name`
+        pub fn extension(mut self, name: &CStr) -> Self {
+            let mut prop = vk::ExtensionProperties::default();
+            copy_name(&mut prop.extension_name, &name.to_string_lossy());
+            self.extensions.push(prop);
+            self
+        }
+
+        /// Makes every queue family present to the surface, reporting the given properties
+        pub fn presenting(
+            mut self,
+            capabilities: vk::SurfaceCapabilitiesKHR,
+            formats: Vec<vk::SurfaceFormatKHR>,
+            present_modes: Vec<vk::PresentModeKHR>,
+        ) -> Self {
+            self.present_families = vec![true; self.queue_families.len()];
+            self.surface_capabilities = capabilities;
+            self.surface_formats = formats;
+            self.present_modes = present_modes;
+            self
+        }
+    }
+
+    /// The machines described in `tests/fixtures`, only read with the surface
+    /// since their snapshots include the swapchain details
+    #[cfg(feature = "surface")]
+    pub mod profile {
+        use super::*;
+
+        /// The names of the profiles in `tests/fixtures`, written by the `dump-profile` example
+        pub const PROFILES: [&str; 5] = [
+            "nvidia-discrete",
+            "amd-apu",
+            "intel-integrated",
+            "mali",
+            "lavapipe",
+        ];
+
+        /// Returns a field of the features
+        type Feature = fn(&mut vk::PhysicalDeviceFeatures) -> &mut vk::Bool32;
+
+        /// The features recorded in the profiles, the others are disabled
+        const FEATURES: [(&str, Feature); 12] = [
+            ("robust_buffer_access", |f| &mut f.robust_buffer_access),
+            ("geometry_shader", |f| &mut f.geometry_shader),
+            ("tessellation_shader", |f| &mut f.tessellation_shader),
+            ("sample_rate_shading", |f| &mut f.sample_rate_shading),
+            ("multi_draw_indirect", |f| &mut f.multi_draw_indirect),
+            ("fill_mode_non_solid", |f| &mut f.fill_mode_non_solid),
+            ("wide_lines", |f| &mut f.wide_lines),
+            ("sampler_anisotropy", |f| &mut f.sampler_anisotropy),
+            ("texture_compression_etc2", |f| {
+                &mut f.texture_compression_etc2
+            }),
+            ("texture_compression_astc_ldr", |f| {
+                &mut f.texture_compression_astc_ldr
+            }),
+            ("texture_compression_bc", |f| &mut f.texture_compression_bc),
+            ("shader_float64", |f| &mut f.shader_float64),
+        ];
+
+        /// The devices of a machine, as written by the `dump-profile` example
+        ///
+        /// Enums and flags are written with their [`Debug`](std::fmt::Debug) representation,
+        /// the extents as `[width, height]`
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        pub struct Profile {
+            /// Where the data comes from
+            pub description: String,
+            devices: Vec<DeviceProfile>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct DeviceProfile {
+            name: String,
+            device_type: String,
+            api_version: String,
+            driver_version: u32,
+            vendor_id: u32,
+            device_id: u32,
+            features: Vec<String>,
+            extensions: Vec<String>,
+            queue_families: Vec<QueueFamilyProfile>,
+            memory_heaps: Vec<MemoryHeapProfile>,
+            memory_types: Vec<MemoryTypeProfile>,
+            /// [`None`] when the profile has been captured without a window
+            surface: Option<SurfaceProfile>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct QueueFamilyProfile {
+            flags: String,
+            count: u32,
+            timestamp_valid_bits: u32,
+            min_image_transfer_granularity: [u32; 3],
+            present: bool,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct MemoryHeapProfile {
+            size: u64,
+            flags: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct MemoryTypeProfile {
+            heap: u32,
+            flags: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct SurfaceProfile {
+            min_image_count: u32,
+            max_image_count: u32,
+            current_extent: [u32; 2],
+            min_image_extent: [u32; 2],
+            max_image_extent: [u32; 2],
+            max_image_array_layers: u32,
+            supported_transforms: String,
+            current_transform: String,
+            supported_composite_alpha: String,
+            supported_usage_flags: String,
+            formats: Vec<[String; 2]>,
+            present_modes: Vec<String>,
+        }
+
+        impl Profile {
+            /// Reads `tests/fixtures/{name}.json`
+            pub fn load(name: &str) -> Self {
+                let path = format!("{}/tests/fixtures/{name}.json", env!("CARGO_MANIFEST_DIR"));
+                let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+                serde_json::from_str(&json).unwrap_or_else(|e| panic!("{path}: {e}"))
+            }
+
+            /// An instance that enumerates the devices of the profile,
+            /// holding a surface if any of them has been captured with one
+            pub fn instance(&self) -> Instance {
+                let devices = self.devices.iter().map(Device::from_profile).collect();
+                let instance = Instance::new(devices);
+                match self.devices.iter().any(|dev| dev.surface.is_some()) {
+                    true => instance.with_surface(),
+                    false => instance,
+                }
+            }
+        }
+
+        impl Device {
+            fn from_profile(profile: &DeviceProfile) -> Self {
+                let version: Vec<u32> = profile
+                    .api_version
+                    .split('.')
+                    .map(|n| n.parse().expect("invalid API version"))
+                    .collect();
+                let &[major, minor, patch] = &version[..] else {
+                    panic!("invalid API version {}", profile.api_version);
+                };
+                let mut properties = vk::PhysicalDeviceProperties {
+                    api_version: vk::make_api_version(0, major, minor, patch),
+                    driver_version: profile.driver_version,
+                    vendor_id: profile.vendor_id,
+                    device_id: profile.device_id,
+                    device_type: enum_value(&profile.device_type, vk::PhysicalDeviceType::from_raw),
+                    ..Default::default()
+                };
+                copy_name(&mut properties.device_name, &profile.name);
+
+                let mut features = vk::PhysicalDeviceFeatures::default();
+                for name in &profile.features {
+                    let (_, feature) = FEATURES
+                        .iter()
+                        .find(|(feature, _)| feature == name)
+                        .unwrap_or_else(|| panic!("unknown feature {name}"));
+                    *feature(&mut features) = vk::TRUE;
+                }
+
+                let queue_families = profile
+                    .queue_families
+                    .iter()
+                    .map(|fam| {
+                        let [width, height, depth] = fam.min_image_transfer_granularity;
+                        vk::QueueFamilyProperties {
+                            queue_flags: flags(&fam.flags, vk::QueueFlags::from_raw),
+                            queue_count: fam.count,
+                            timestamp_valid_bits: fam.timestamp_valid_bits,
+                            min_image_transfer_granularity: vk::Extent3D {
+                                width,
+                                height,
+                                depth,
+                            },
+                        }
+                    })
+                    .collect();
+
+                let mut memory = vk::PhysicalDeviceMemoryProperties {
+                    memory_heap_count: profile.memory_heaps.len() as u32,
+                    memory_type_count: profile.memory_types.len() as u32,
+                    ..Default::default()
+                };
+                for (dst, heap) in memory.memory_heaps.iter_mut().zip(&profile.memory_heaps) {
+                    *dst = vk::MemoryHeap {
+                        size: heap.size,
+                        flags: flags(&heap.flags, vk::MemoryHeapFlags::from_raw),
+                    };
+                }
+                for (dst, ty) in memory.memory_types.iter_mut().zip(&profile.memory_types) {
+                    *dst = vk::MemoryType {
+                        property_flags: flags(&ty.flags, vk::MemoryPropertyFlags::from_raw),
+                        heap_index: ty.heap,
+                    };
+                }
+
+                let mut device = Self {
+                    properties,
+                    features,
+                    queue_families,
+                    memory,
+                    present_families: profile.queue_families.iter().map(|f| f.present).collect(),
+                    ..Default::default()
+                };
+                for name in &profile.extensions {
+                    let name = std::ffi::CString::new(name.as_str()).unwrap();
+                    device = device.extension(&name);
+                }
+                if let Some(surface) = &profile.surface {
+                    device.surface_capabilities = surface.capabilities();
+                    device.surface_formats = surface
+                        .formats
+                        .iter()
+                        .map(|[format, color_space]| vk::SurfaceFormatKHR {
+                            format: enum_value(format, vk::Format::from_raw),
+                            color_space: enum_value(color_space, vk::ColorSpaceKHR::from_raw),
+                        })
+                        .collect();
+                    device.present_modes = surface
+                        .present_modes
+                        .iter()
+                        .map(|mode| enum_value(mode, vk::PresentModeKHR::from_raw))
+                        .collect();
+                }
+                device
+            }
+        }
+
+        impl SurfaceProfile {
+            fn capabilities(&self) -> vk::SurfaceCapabilitiesKHR {
+                let extent = |[width, height]: [u32; 2]| vk::Extent2D { width, height };
+                vk::SurfaceCapabilitiesKHR {
+                    min_image_count: self.min_image_count,
+                    max_image_count: self.max_image_count,
+                    current_extent: extent(self.current_extent),
+                    min_image_extent: extent(self.min_image_extent),
+                    max_image_extent: extent(self.max_image_extent),
+                    max_image_array_layers: self.max_image_array_layers,
+                    supported_transforms: flags(
+                        &self.supported_transforms,
+                        vk::SurfaceTransformFlagsKHR::from_raw,
+                    ),
+                    current_transform: flags(
+                        &self.current_transform,
+                        vk::SurfaceTransformFlagsKHR::from_raw,
+                    ),
+                    supported_composite_alpha: flags(
+                        &self.supported_composite_alpha,
+                        vk::CompositeAlphaFlagsKHR::from_raw,
+                    ),
+                    supported_usage_flags: flags(
+                        &self.supported_usage_flags,
+                        vk::ImageUsageFlags::from_raw,
+                    ),
+                }
+            }
+        }
+
+        /// Parses the [`Debug`](std::fmt::Debug) representation of a Vulkan enum
+        ///
+        /// The names are looked up in the ranges of the core values and of the extensions
+        /// that the profiles use, unknown values are written as numbers
+        fn enum_value<T: std::fmt::Debug>(name: &str, from_raw: fn(i32) -> T) -> T {
+            const RANGES: [std::ops::RangeInclusive<i32>; 5] = [
+                0..=184,
+                1_000_054_000..=1_000_054_007,
+                1_000_104_001..=1_000_104_014,
+                1_000_111_000..=1_000_111_001,
+                1_000_156_000..=1_000_156_033,
+            ];
+            if let Ok(raw) = name.parse() {
+                return from_raw(raw);
+            }
+            RANGES
+                .into_iter()
+                .flatten()
+                .map(from_raw)
+                .find(|value| format!("{value:?}") == name)
+                .unwrap_or_else(|| panic!("unknown enum value {name}"))
+        }
+
+        /// Parses the [`Debug`](std::fmt::Debug) representation of Vulkan flags,
+        /// the names of the bits separated by `|` and the unknown bits written in binary
+        fn flags<T: std::fmt::Debug>(text: &str, from_raw: fn(vk::Flags) -> T) -> T {
+            let raw = text
+                .split('|')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    (0..vk::Flags::BITS)
+                        .map(|bit| 1 << bit)
+                        .find(|&bit| format!("{:?}", from_raw(bit)) == name)
+                        .or_else(|| vk::Flags::from_str_radix(name, 2).ok())
+                        .unwrap_or_else(|| panic!("unknown flag {name}"))
+                })
+                .fold(0, |acc, bit| acc | bit);
+            from_raw(raw)
+        }
+    }
+
+    /// Writes `name` in the fixed size, null-terminated, array `dst`
+    pub fn copy_name<const N: usize>(dst: &mut [c_char; N], name: &str) {
+        for (dst, &src) in dst[..N - 1].iter_mut().zip(name.as_bytes()) {
+            *dst = src as c_char;
+        }
+    }
+
+    /// An entry whose functions are never loaded, apart from `vkGetDeviceProcAddr`
+    /// which loads no function either, so that the device tables can be built
+    fn unloaded_entry() -> ash::Entry {
+        unsafe extern "system" fn get_device_proc_addr(
+            _device: vk::Device,
+            _name: *const c_char,
+        ) -> vk::PFN_vkVoidFunction {
+            None
+        }
+        unsafe extern "system" fn get_instance_proc_addr(
+            _instance: vk::Instance,
+            name: *const c_char,
+        ) -> vk::PFN_vkVoidFunction {
+            let get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr = get_device_proc_addr;
+            (CStr::from_ptr(name) == cstr!(vkGetDeviceProcAddr))
+                .then(|| std::mem::transmute(get_device_proc_addr))
+        }
+        unsafe {
+            ash::Entry::from_static_fn(vk::StaticFn {
+                get_instance_proc_addr,
+            })
+        }
+    }
+
+    /// Converts the index of a device in the fixtures to its handle, never null
+    fn handle(index: usize) -> vk::PhysicalDevice {
+        vk::Handle::from_raw(index as u64 + 1)
+    }
+
+    /// An [`InstanceHolder`](crate::InstanceHolder) whose calls are answered with fixture data
+    pub struct Instance {
+        entry: ash::Entry,
+        instance: ash::Instance,
+        #[cfg(feature = "surface")]
+        surface_fns: khr::Surface,
+        info: crate::InstanceInfo,
+        devices: Vec<Device>,
+        /// The surface reported to the holders, if any
+        surface: vk::SurfaceKHR,
+        #[cfg(feature = "surface")]
+        cache: crate::surface::SurfaceInfoCache,
+        /// The number of devices created and not destroyed yet
+        live_devices: Rc<Cell<usize>>,
+    }
+
+    impl Instance {
+        /// An instance for Vulkan 1.3, without a surface, that enumerates `devices`
+        pub fn new(devices: Vec<Device>) -> Self {
+            let entry = unloaded_entry();
+            let instance =
+                unsafe { ash::Instance::load(entry.static_fn(), vk::Handle::from_raw(1)) };
+            Self {
+                #[cfg(feature = "surface")]
+                surface_fns: khr::Surface::new(&entry, &instance),
+                entry,
+                instance,
+                info: crate::InstanceInfo {
+                    api_version: vk::API_VERSION_1_3,
+                    layers: vec![],
+                    extensions: vec![],
+                    validation: Default::default(),
+                },
+                devices,
+                surface: vk::SurfaceKHR::null(),
+                #[cfg(feature = "surface")]
+                cache: Default::default(),
+                live_devices: Default::default(),
+            }
+        }
+
+        /// Holds a surface along with the instance
+        pub fn with_surface(self) -> Self {
+            Self {
+                surface: vk::Handle::from_raw(1),
+                ..self
+            }
+        }
+
+        /// Returns the handle of the device at `index` in the fixtures
+        pub fn device_handle(index: usize) -> vk::PhysicalDevice {
+            handle(index)
+        }
+
+        /// Returns the number of devices created and not destroyed yet,
+        /// which keeps being updated after the instance is moved into a holder
+        pub fn live_devices(&self) -> Rc<Cell<usize>> {
+            self.live_devices.clone()
+        }
+
+        fn device(&self, device: vk::PhysicalDevice) -> &Device {
+            let index = vk::Handle::as_raw(device) as usize - 1;
+            &self.devices[index]
+        }
+    }
+
+    impl Backend for Instance {
+        fn handle(&self) -> vk::Instance {
+            self.instance.handle()
+        }
+
+        unsafe fn enumerate_physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>> {
+            Ok((0..self.devices.len()).map(handle).collect())
+        }
+
+        unsafe fn get_physical_device_properties(
+            &self,
+            device: vk::PhysicalDevice,
+        ) -> vk::PhysicalDeviceProperties {
+            self.device(device).properties
+        }
+
+        unsafe fn get_physical_device_features(
+            &self,
+            device: vk::PhysicalDevice,
+        ) -> vk::PhysicalDeviceFeatures {
+            self.device(device).features
+        }
+
+        unsafe fn get_physical_device_queue_family_properties(
+            &self,
+            device: vk::PhysicalDevice,
+        ) -> Vec<vk::QueueFamilyProperties> {
+            self.device(device).queue_families.clone()
+        }
+
+        unsafe fn get_physical_device_memory_properties(
+            &self,
+            device: vk::PhysicalDevice,
+        ) -> vk::PhysicalDeviceMemoryProperties {
+            self.device(device).memory
+        }
+
+        unsafe fn enumerate_device_extension_properties(
+            &self,
+            device: vk::PhysicalDevice,
+        ) -> VkResult<Vec<vk::ExtensionProperties>> {
+            let device = self.device(device);
+            match device.extensions_error {
+                Some(err) => Err(err),
+                None => Ok(device.extensions.clone()),
+            }
+        }
+
+        unsafe fn create_device(
+            &self,
+            _device: vk::PhysicalDevice,
+            _create_info: &vk::DeviceCreateInfo,
+            _allocator: Option<&vk::AllocationCallbacks>,
+        ) -> VkResult<ash::Device> {
+            self.live_devices.set(self.live_devices.get() + 1);
+            Ok(ash::Device::load(
+                self.instance.fp_v1_0(),
+                vk::Handle::from_raw(1),
+            ))
+        }
+
+        unsafe fn get_device_queue(
+            &self,
+            _device: &ash::Device,
+            queue_family_index: u32,
+            queue_index: u32,
+        ) -> vk::Queue {
+            // Unique and never null
+            vk::Handle::from_raw(((queue_family_index as u64) << 32) | (queue_index as u64 + 1))
+        }
+
+        unsafe fn destroy_device(
+            &self,
+            _device: &ash::Device,
+            _allocator: Option<&vk::AllocationCallbacks>,
+        ) {
+            self.live_devices.set(self.live_devices.get() - 1);
+        }
+    }
+
+    impl SurfaceBackend for Instance {
+        unsafe fn get_physical_device_surface_support(
+            &self,
+            device: vk::PhysicalDevice,
+            queue_family_index: u32,
+            _surface: vk::SurfaceKHR,
+        ) -> VkResult<bool> {
+            let device = self.device(device);
+            if let Some(err) = device.present_error {
+                return Err(err);
+            }
+            if queue_family_index as usize >= device.queue_families.len() {
+                return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+            }
+            let present = &device.present_families;
+            Ok(present.get(queue_family_index as usize) == Some(&true))
+        }
+
+        unsafe fn get_physical_device_surface_capabilities(
+            &self,
+            device: vk::PhysicalDevice,
+            _surface: vk::SurfaceKHR,
+        ) -> VkResult<vk::SurfaceCapabilitiesKHR> {
+            Ok(self.device(device).surface_capabilities)
+        }
+
+        unsafe fn get_physical_device_surface_formats(
+            &self,
+            device: vk::PhysicalDevice,
+            _surface: vk::SurfaceKHR,
+        ) -> VkResult<Vec<vk::SurfaceFormatKHR>> {
+            Ok(self.device(device).surface_formats.clone())
+        }
+
+        unsafe fn get_physical_device_surface_present_modes(
+            &self,
+            device: vk::PhysicalDevice,
+            _surface: vk::SurfaceKHR,
+        ) -> VkResult<Vec<vk::PresentModeKHR>> {
+            Ok(self.device(device).present_modes.clone())
+        }
+    }
+
+    impl crate::instance::pvt::InstanceHolder for Instance {
+        fn vk_instance(&self) -> &ash::Instance {
+            &self.instance
+        }
+
+        fn vk_entry(&self) -> &ash::Entry {
+            &self.entry
+        }
+
+        fn vk_allocator(&self) -> Option<&vk::AllocationCallbacks> {
+            None
+        }
+
+        fn vk_info(&self) -> &crate::InstanceInfo {
+            &self.info
+        }
+
+        fn vk_backend(&self) -> &dyn Backend {
+            self
+        }
+
+        fn vk_presentation_surface(&self) -> Option<(&dyn SurfaceBackend, vk::SurfaceKHR)> {
+            let surface = self.surface;
+            (surface != vk::SurfaceKHR::null()).then_some((self as &dyn SurfaceBackend, surface))
+        }
+    }
+
+    #[cfg(feature = "surface")]
+    impl crate::surface::pvt::SurfaceHolder for Instance {
+        fn vk_surface_fns(&self) -> &khr::Surface {
+            &self.surface_fns
+        }
+
+        fn vk_surface_backend(&self) -> &dyn SurfaceBackend {
+            self
+        }
+
+        fn vk_surface(&self) -> &vk::SurfaceKHR {
+            &self.surface
+        }
+
+        fn vk_surface_mut(&mut self) -> &mut vk::SurfaceKHR {
+            &mut self.surface
+        }
+
+        fn vk_surface_cache(&self) -> &crate::surface::SurfaceInfoCache {
+            &self.cache
+        }
+    }
+}
@@ -0,0 +1,181 @@
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use std::ffi::CStr;
+
+use ash::vk;
+
+/// A summary of what a physical device can do, meant for the decisions most
+/// applications take at startup
+///
+/// Returned by [`vku::PhysicalDevRef::capabilities`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceCapabilities {
+    /// Whether or not any queue family can present to the surface held along with the instance,
+    /// [`None`] if there is no surface
+    pub can_present: Option<bool>,
+    /// Whether or not any queue family supports compute operations
+    pub compute_queue: bool,
+    /// The index of a queue family that supports transfers but neither graphics nor compute,
+    /// usually backed by a DMA engine
+    pub dedicated_transfer_family: Option<u32>,
+    /// The maximum width and height of a 2D image
+    pub max_texture_size: u32,
+    /// The maximum number of samples supported by both color and depth framebuffer attachments
+    pub max_msaa_samples: u32,
+    /// Whether or not the BC compressed formats are supported
+    pub bc_compression: bool,
+    /// Whether or not the ETC2 and EAC compressed formats are supported
+    pub etc2_compression: bool,
+    /// Whether or not the ASTC LDR compressed formats are supported
+    pub astc_compression: bool,
+    /// Whether or not the ray tracing pipeline and acceleration structure extensions are supported
+    pub ray_tracing: bool,
+    /// Whether or not a mesh shader extension is supported
+    pub mesh_shader: bool,
+    /// The maximum size in bytes of the push constants
+    pub max_push_constants_size: u32,
+}
+
+/// How a device provides a feature that has been promoted to core in a later Vulkan version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provider {
+    /// The feature is part of the Vulkan version in use, the core entry points must be used
+    Core,
+    /// The feature is provided by the extension, whose entry points must be used
+    Extension,
+    /// The feature is not available
+    Unavailable,
+}
+
+impl Provider {
+    /// Returns whether or not the feature is available
+    pub fn is_available(self) -> bool {
+        self != Self::Unavailable
+    }
+}
+
+/// Which of the features promoted to core are available on a device and through what
+///
+/// The features must still be enabled in the corresponding features structure
+/// when creating the device, regardless of how they are provided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceCapabilitiesResolved {
+    /// The Vulkan version that can be used with the device, which is the lowest between
+    /// the one of the instance and the one supported by the device
+    pub api_version: u32,
+    /// Core in Vulkan 1.3, or `VK_KHR_dynamic_rendering`
+    pub dynamic_rendering: Provider,
+    /// Core in Vulkan 1.3, or `VK_KHR_synchronization2`
+    pub synchronization2: Provider,
+    /// Core in Vulkan 1.2, or `VK_KHR_timeline_semaphore`
+    pub timeline_semaphore: Provider,
+    /// Core in Vulkan 1.2, or `VK_EXT_host_query_reset`
+    pub host_query_reset: Provider,
+}
+
+impl DeviceCapabilitiesResolved {
+    /// Resolves the provider of each feature for a device used with `api_version`,
+    /// where `has_extension` tells whether or not an extension is available
+    pub fn resolve(api_version: u32, mut has_extension: impl FnMut(&CStr) -> bool) -> Self {
+        let mut provider = |core: u32, extension: &CStr| {
+            if api_version >= core {
+                Provider::Core
+            } else if has_extension(extension) {
+                Provider::Extension
+            } else {
+                Provider::Unavailable
+            }
+        };
+        Self {
+            api_version,
+            dynamic_rendering: provider(vk::API_VERSION_1_3, vk::KhrDynamicRenderingFn::name()),
+            synchronization2: provider(vk::API_VERSION_1_3, vk::KhrSynchronization2Fn::name()),
+            timeline_semaphore: provider(vk::API_VERSION_1_2, vk::KhrTimelineSemaphoreFn::name()),
+            host_query_reset: provider(vk::API_VERSION_1_2, vk::ExtHostQueryResetFn::name()),
+        }
+    }
+
+    /// Returns the extensions that must be enabled to use the available features,
+    /// leaving out the ones already provided by the core version
+    pub fn extensions(&self) -> Vec<&'static CStr> {
+        [
+            (self.dynamic_rendering, vk::KhrDynamicRenderingFn::name()),
+            (self.synchronization2, vk::KhrSynchronization2Fn::name()),
+            (self.timeline_semaphore, vk::KhrTimelineSemaphoreFn::name()),
+            (self.host_query_reset, vk::ExtHostQueryResetFn::name()),
+        ]
+        .into_iter()
+        .filter(|(provider, _)| *provider == Provider::Extension)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resolves the features for a device profile with the given version and extensions
+    fn profile(api_version: u32, extensions: &[&CStr]) -> DeviceCapabilitiesResolved {
+        DeviceCapabilitiesResolved::resolve(api_version, |name| extensions.contains(&name))
+    }
+
+    #[test]
+    fn vulkan_1_0_without_extensions() {
+        let resolved = profile(vk::API_VERSION_1_0, &[]);
+        assert_eq!(resolved.dynamic_rendering, Provider::Unavailable);
+        assert_eq!(resolved.synchronization2, Provider::Unavailable);
+        assert_eq!(resolved.timeline_semaphore, Provider::Unavailable);
+        assert_eq!(resolved.host_query_reset, Provider::Unavailable);
+        assert!(resolved.extensions().is_empty());
+    }
+
+    #[test]
+    fn vulkan_1_1_with_extensions() {
+        // e.g. an older mobile driver
+        let exts = [
+            vk::KhrTimelineSemaphoreFn::name(),
+            vk::ExtHostQueryResetFn::name(),
+        ];
+        let resolved = profile(vk::make_api_version(0, 1, 1, 128), &exts);
+        assert_eq!(resolved.dynamic_rendering, Provider::Unavailable);
+        assert_eq!(resolved.synchronization2, Provider::Unavailable);
+        assert_eq!(resolved.timeline_semaphore, Provider::Extension);
+        assert_eq!(resolved.host_query_reset, Provider::Extension);
+        assert_eq!(resolved.extensions(), exts);
+    }
+
+    #[test]
+    fn vulkan_1_2_with_extensions() {
+        // e.g. a desktop driver stuck on 1.2 with the 1.3 features as extensions
+        let exts = [
+            vk::KhrDynamicRenderingFn::name(),
+            vk::KhrSynchronization2Fn::name(),
+            vk::KhrTimelineSemaphoreFn::name(),
+            vk::ExtHostQueryResetFn::name(),
+        ];
+        let resolved = profile(vk::make_api_version(0, 1, 2, 198), &exts);
+        assert_eq!(resolved.dynamic_rendering, Provider::Extension);
+        assert_eq!(resolved.synchronization2, Provider::Extension);
+        assert_eq!(resolved.timeline_semaphore, Provider::Core);
+        assert_eq!(resolved.host_query_reset, Provider::Core);
+        // The extensions promoted to core are not needed
+        assert_eq!(resolved.extensions(), &exts[..2]);
+    }
+
+    #[test]
+    fn vulkan_1_3_needs_no_extensions() {
+        let exts = [
+            vk::KhrDynamicRenderingFn::name(),
+            vk::KhrSynchronization2Fn::name(),
+        ];
+        let resolved = profile(vk::make_api_version(0, 1, 3, 250), &exts);
+        assert_eq!(resolved.dynamic_rendering, Provider::Core);
+        assert_eq!(resolved.synchronization2, Provider::Core);
+        assert_eq!(resolved.timeline_semaphore, Provider::Core);
+        assert_eq!(resolved.host_query_reset, Provider::Core);
+        assert!(resolved.extensions().is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use std::ffi::c_void;
+
+use ash::vk;
+
+/// Inserts the structures chained starting from `next` at the front of the `p_next` chain
+///
+/// This is what the `push_next` methods of the ash builders do, but it can be used
+/// after the builder has been consumed.
+///
+/// # Safety
+///
+/// `next` must point to a valid Vulkan structure whose `p_next` chain is valid
+/// and that can extend the structure owning `p_next`
+pub(super) unsafe fn prepend(p_next: &mut *const c_void, next: *mut vk::BaseOutStructure) {
+    let mut last = next;
+    while !(*last).p_next.is_null() {
+        last = (*last).p_next;
+    }
+    (*last).p_next = *p_next as *mut _;
+    *p_next = next as *const c_void;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the structure types in the chain starting from `p_next`
+    fn types(mut p_next: *const c_void) -> Vec<vk::StructureType> {
+        let mut types = vec![];
+        while !p_next.is_null() {
+            let base = unsafe { &*(p_next as *const vk::BaseOutStructure) };
+            types.push(base.s_type);
+            p_next = base.p_next as *const c_void;
+        }
+        types
+    }
+
+    #[test]
+    fn prepend_to_empty_chain() {
+        let mut features = vk::PhysicalDeviceVulkan12Features::default();
+        let mut info = vk::DeviceCreateInfo::default();
+        unsafe { prepend(&mut info.p_next, &mut features as *mut _ as *mut _) };
+        assert_eq!(
+            types(info.p_next),
+            [vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES]
+        );
+    }
+
+    #[test]
+    fn prepend_keeps_the_existing_chain() {
+        let mut v11 = vk::PhysicalDeviceVulkan11Features::default();
+        let mut v12 = vk::PhysicalDeviceVulkan12Features::default();
+        let mut v13 = vk::PhysicalDeviceVulkan13Features::default();
+        let info = vk::DeviceCreateInfo::builder().push_next(&mut v13);
+        let mut info = info.build();
+
+        // Prepending in reverse order keeps the order in which the structures are listed
+        for next in [
+            &mut v12 as *mut _ as *mut vk::BaseOutStructure,
+            &mut v11 as *mut _ as *mut _,
+        ] {
+            unsafe { prepend(&mut info.p_next, next) };
+        }
+        assert_eq!(
+            types(info.p_next),
+            [
+                vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_1_FEATURES,
+                vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES,
+                vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES,
+            ]
+        );
+    }
+
+    #[test]
+    fn prepend_a_whole_chain() {
+        let mut dynamic_rendering = vk::PhysicalDeviceDynamicRenderingFeatures::default();
+        let mut sync2 = vk::PhysicalDeviceSynchronization2Features {
+            p_next: &mut dynamic_rendering as *mut _ as *mut c_void,
+            ..Default::default()
+        };
+        let mut host_query_reset = vk::PhysicalDeviceHostQueryResetFeatures::default();
+        let mut info = vk::DeviceCreateInfo::builder()
+            .push_next(&mut host_query_reset)
+            .build();
+
+        unsafe { prepend(&mut info.p_next, &mut sync2 as *mut _ as *mut _) };
+        assert_eq!(
+            types(info.p_next),
+            [
+                vk::StructureType::PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES,
+                vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES,
+                vk::StructureType::PHYSICAL_DEVICE_HOST_QUERY_RESET_FEATURES,
+            ]
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use std::ffi;
+#[cfg(not(feature = "tracing"))]
+use std::io::Write;
 
 use ash::{extensions::ext, vk};
 
+use super::{ErrorContext, Operation};
+
 /// A Vulkan debug utils extension callback
 ///
 /// This function will handle the debug messages generated by the debug utils extension
@@ -9,27 +13,80 @@ unsafe extern "system" fn vk_debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     message_type: vk::DebugUtilsMessageTypeFlagsEXT,
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _user_data: *mut std::os::raw::c_void,
+    user_data: *mut std::os::raw::c_void,
 ) -> vk::Bool32 {
-    // Filter based on the flags
+    let data = p_callback_data.as_ref();
+    let msg = match data {
+        Some(data) if !data.p_message.is_null() => Some(ffi::CStr::from_ptr(data.p_message)),
+        _ => None,
+    };
+
+    // SAFETY: When not null the user data is the sink owned by the DebugUtils
+    let sink = (user_data as *const PrintfSink).as_ref();
+    if let (Some(sink), Some(msg)) = (sink, msg) {
+        if data.is_some_and(|data| is_debug_printf(data)) {
+            // Unwinding across the FFI boundary would abort the process
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                sink(&msg.to_string_lossy())
+            }));
+            return vk::FALSE;
+        }
+    }
+
+    // Filter based on the flags, the output of debug printf is reported with
+    // the INFO severity so let it through
     if (message_severity == vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
         || message_severity == vk::DebugUtilsMessageSeverityFlagsEXT::INFO)
         && message_type == vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+        && !data.is_some_and(|data| is_debug_printf(data))
     {
         return vk::FALSE;
     }
 
-    eprintln!("[ {:?} ] [ {:?} ]", message_severity, message_type);
-    if !p_callback_data.is_null() {
-        let msg = ffi::CStr::from_ptr((*p_callback_data).p_message);
-        match msg.to_str() {
-            Ok(str) => eprintln!("{}", str),
-            Err(_) => eprintln!("{:?}", msg),
+    // With the `tracing` feature the messages are emitted as events
+    #[cfg(feature = "tracing")]
+    {
+        use vk::DebugUtilsMessageSeverityFlagsEXT as Sev;
+        let msg = msg.map(ffi::CStr::to_string_lossy).unwrap_or_default();
+        match message_severity {
+            Sev::ERROR => tracing::error!(ty = ?message_type, "{msg}"),
+            Sev::WARNING => tracing::warn!(ty = ?message_type, "{msg}"),
+            Sev::INFO => tracing::info!(ty = ?message_type, "{msg}"),
+            _ => tracing::debug!(ty = ?message_type, "{msg}"),
+        }
+    }
+
+    // Panicking here would abort the process since it would unwind across the FFI boundary,
+    // so don't use `eprintln!` which panics when it fails to write to stderr
+    #[cfg(not(feature = "tracing"))]
+    {
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "[ {:?} ] [ {:?} ]", message_severity, message_type);
+        if let Some(msg) = msg {
+            let _ = match msg.to_str() {
+                Ok(str) => writeln!(stderr, "{}", str),
+                Err(_) => writeln!(stderr, "{:?}", msg),
+            };
         }
     }
     vk::FALSE
 }
 
+/// Returns whether or not the message is the output of a shader `debugPrintfEXT`
+///
+/// # Safety
+///
+/// The pointers in `data` must be either null or valid
+unsafe fn is_debug_printf(data: &vk::DebugUtilsMessengerCallbackDataEXT) -> bool {
+    if data.p_message_id_name.is_null() {
+        return false;
+    }
+    let id = ffi::CStr::from_ptr(data.p_message_id_name);
+    super::validation::DEBUG_PRINTF_MESSAGE_IDS
+        .iter()
+        .any(|name| id.to_bytes() == name.as_bytes())
+}
+
 /// Returns the default [`vk::DebugUtilsMessengerCreateInfoEXT`] settings
 ///
 /// Those are:
@@ -50,6 +107,10 @@ pub fn create_info() -> vk::DebugUtilsMessengerCreateInfoEXT {
         .build()
 }
 
+/// A function that receives the output of the shaders `debugPrintfEXT`,
+/// check [`DebugUtils::with_printf_sink`]
+pub type PrintfSink = Box<dyn Fn(&str) + Send + Sync>;
+
 /// A wrapper around all the necessary state needed to hold a Vulkan debug utils extension context.
 ///
 /// The Vulkan debug utils extension provides a way to handle logs generated by Vulkan functions by
@@ -58,20 +119,49 @@ pub struct DebugUtils<I: super::InstanceHolder> {
     instance: I,
     context: ext::DebugUtils,
     messenger: vk::DebugUtilsMessengerEXT,
+    /// Where the debug printf messages are forwarded to, boxed twice to pass
+    /// a thin pointer to the callback which must stay at the same address.
+    // Only held to keep the sink alive while the messenger uses it
+    #[allow(dead_code)]
+    printf_sink: Option<Box<PrintfSink>>,
 }
 
 impl<I: super::InstanceHolder> DebugUtils<I> {
     /// Creates a Vulkan debug utils extension messenger for to the Vulkan instance `instance`
     pub fn new(instance: I) -> super::Result<Self> {
+        Self::create(instance, None)
+    }
+
+    /// Same as [`new`](Self::new), but the output of the shaders `debugPrintfEXT`
+    /// is forwarded to `sink` instead of being reported with the other messages
+    ///
+    /// Debug printf must be enabled in the [`ValidationConfig`](super::ValidationConfig)
+    /// of the instance for the shaders to produce any output, and the shaders must be compiled
+    /// with it, check [`uses_debug_printf`](super::validation::uses_debug_printf).
+    pub fn with_printf_sink(instance: I, sink: PrintfSink) -> super::Result<Self> {
+        Self::create(instance, Some(Box::new(sink)))
+    }
+
+    fn create(instance: I, printf_sink: Option<Box<PrintfSink>>) -> super::Result<Self> {
         let context = ext::DebugUtils::new(instance.vk_entry(), instance.vk_instance());
-        let messenger_create_info = create_info();
-        let messenger =
-            unsafe { context.create_debug_utils_messenger(&messenger_create_info, None)? };
+        let mut messenger_create_info = create_info();
+        if let Some(sink) = &printf_sink {
+            messenger_create_info.p_user_data = &**sink as *const PrintfSink as *mut _;
+        }
+        let messenger = unsafe {
+            context
+                .create_debug_utils_messenger(&messenger_create_info, instance.vk_allocator())
+                .context_of(
+                    Operation::CreateDebugUtilsMessenger,
+                    instance.vk_instance().handle(),
+                )?
+        };
 
         Ok(Self {
             instance,
             context,
             messenger,
+            printf_sink,
         })
     }
 }
@@ -80,10 +170,19 @@ impl<I: super::InstanceHolder> Drop for DebugUtils<I> {
     fn drop(&mut self) {
         unsafe {
             self.context
-                .destroy_debug_utils_messenger(self.messenger, None);
+                .destroy_debug_utils_messenger(self.messenger, self.instance.vk_allocator());
         }
     }
 }
 
+impl<I: super::InstanceHolder> super::RawHandle for DebugUtils<I> {
+    type Handle = vk::DebugUtilsMessengerEXT;
+
+    fn raw(&self) -> vk::DebugUtilsMessengerEXT {
+        self.messenger
+    }
+}
+
 derive_instance_holder!(DebugUtils<I> = instance: I);
+#[cfg(feature = "surface")]
 derive_surface_holder!(DebugUtils<I> = instance: I);
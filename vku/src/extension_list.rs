@@ -0,0 +1,110 @@
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+};
+
+/// An owned list of extension names, passed when creating instances and devices
+///
+/// The names are stored as [`CString`]s, so that the pointers handed to Vulkan
+/// are guaranteed to be valid for as long as the list lives.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtensionList {
+    names: Vec<CString>,
+}
+
+/// An owned list of layer names, passed when creating instances
+pub type LayerList = ExtensionList;
+
+impl ExtensionList {
+    /// Creates an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies the names pointed by `names`
+    ///
+    /// # Safety
+    ///
+    /// `names` must contain pointers to null-terminated strings
+    pub unsafe fn from_raw(names: &[*const c_char]) -> Self {
+        names.iter().map(|&n| CStr::from_ptr(n)).collect()
+    }
+
+    /// Adds `name` to the list, unless it's already there
+    pub fn push(&mut self, name: &CStr) {
+        if !self.contains(name) {
+            self.names.push(name.to_owned());
+        }
+    }
+
+    /// Returns whether or not `name` is in the list
+    pub fn contains(&self, name: &CStr) -> bool {
+        self.names.iter().any(|n| n.as_c_str() == name)
+    }
+
+    /// Returns the names in the list
+    pub fn names(&self) -> &[CString] {
+        &self.names
+    }
+
+    /// Returns an iterator over the names in the list
+    pub fn iter(&self) -> impl Iterator<Item = &CStr> {
+        self.names.iter().map(CString::as_c_str)
+    }
+
+    /// Returns the number of names in the list
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns whether or not the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the pointers to the names, valid as long as the list is not modified or dropped
+    pub(super) fn as_ptrs(&self) -> Vec<*const c_char> {
+        self.names.iter().map(|n| n.as_ptr()).collect()
+    }
+}
+
+impl<'a> FromIterator<&'a CStr> for ExtensionList {
+    fn from_iter<T: IntoIterator<Item = &'a CStr>>(iter: T) -> Self {
+        let mut list = Self::new();
+        iter.into_iter().for_each(|name| list.push(name));
+        list
+    }
+}
+
+impl<'a> Extend<&'a CStr> for ExtensionList {
+    fn extend<T: IntoIterator<Item = &'a CStr>>(&mut self, iter: T) {
+        iter.into_iter().for_each(|name| self.push(name));
+    }
+}
+
+impl From<&[&CStr]> for ExtensionList {
+    fn from(names: &[&CStr]) -> Self {
+        names.iter().copied().collect()
+    }
+}
+
+impl<const N: usize> From<[&CStr; N]> for ExtensionList {
+    fn from(names: [&CStr; N]) -> Self {
+        names.into_iter().collect()
+    }
+}
+
+impl From<Vec<CString>> for ExtensionList {
+    fn from(names: Vec<CString>) -> Self {
+        names.iter().map(CString::as_c_str).collect()
+    }
+}
+
+impl From<&ExtensionList> for ExtensionList {
+    fn from(list: &ExtensionList) -> Self {
+        list.clone()
+    }
+}
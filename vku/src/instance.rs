@@ -1,6 +1,36 @@
 use ash::vk;
 use cstr::cstr;
-use std::{ffi::CStr, os::raw};
+use std::{
+    ffi::{CStr, CString},
+    marker::PhantomData,
+    os::raw,
+    sync::Arc,
+};
+
+use super::{
+    allocator::Callbacks, ErrorContext, ExtensionList, HostAllocator, LayerList, Operation,
+    ValidationConfig,
+};
+
+/// The parameters a Vulkan instance has been created with
+#[derive(Clone, Debug)]
+pub struct InstanceInfo {
+    /// The Vulkan API version requested by the application
+    pub api_version: u32,
+    /// The names of the enabled layers
+    pub layers: Vec<CString>,
+    /// The names of the enabled extensions
+    pub extensions: Vec<CString>,
+    /// The enabled features of the validation layers
+    pub validation: ValidationConfig,
+}
+
+impl InstanceInfo {
+    /// Returns whether or not the extension `name` has been enabled
+    pub fn has_extension(&self, name: &CStr) -> bool {
+        self.extensions.iter().any(|ext| ext.as_c_str() == name)
+    }
+}
 
 /// A wrapper around all the necessary state needed to hold a Vulkan instance.
 ///
@@ -12,6 +42,12 @@ pub struct Instance<'a> {
     /// The Vulkan entry point: a set of function pointers to Vulkan functions
     // TODO: this can probably be cloned
     entry: &'a ash::Entry,
+    /// The host memory allocator used for all the objects created through this instance
+    allocator: Option<Callbacks>,
+    /// The parameters this instance has been created with
+    info: InstanceInfo,
+    /// Whether or not the instance is destroyed when this value is dropped
+    owned: bool,
 }
 
 impl<'a> Instance<'a> {
@@ -21,46 +57,399 @@ impl<'a> Instance<'a> {
     ///
     /// In debug mode adds a [`vk::DebugUtilsMessengerCreateInfoEXT`] struct to the [`vk::InstanceCreateInfo`],
     /// which enables the debuf utils extension for the instance related calls.
+    pub fn new(
+        entry: &'a ash::Entry,
+        validation_layers_names: impl Into<LayerList>,
+        extensions_names: impl Into<ExtensionList>,
+        app_name: &CStr,
+    ) -> super::Result<Self> {
+        let builder = Self::builder(entry, app_name)
+            .layers(validation_layers_names)
+            .extensions(extensions_names);
+        // SAFETY: The builder holds owned names and no structure in the `p_next` chain
+        unsafe { builder.build() }
+    }
+
+    /// Same as [`new`](Self::new) but takes the names as raw pointers
     ///
     /// # Safety
     ///
     /// `validation_layers_names` and `extensions_names` must contain pointers to null-terminated strings,
     /// they should be considered as [slice](std::slice)s of [`&CStr`](CStr)
-    pub unsafe fn new(
+    pub unsafe fn new_raw(
         entry: &'a ash::Entry,
         validation_layers_names: &[*const raw::c_char],
         extensions_names: &[*const raw::c_char],
         app_name: &CStr,
     ) -> super::Result<Self> {
+        Self::new(
+            entry,
+            LayerList::from_raw(validation_layers_names),
+            ExtensionList::from_raw(extensions_names),
+            app_name,
+        )
+    }
+
+    /// Initializes a new Vulkan instance that uses `allocator` for the host memory allocations
+    /// made by the Vulkan implementation
+    ///
+    /// The allocator is used by this instance and all the objects created through it,
+    /// apart from that it behaves like [`new`](Self::new).
+    pub fn with_allocator(
+        entry: &'a ash::Entry,
+        validation_layers_names: impl Into<LayerList>,
+        extensions_names: impl Into<ExtensionList>,
+        app_name: &CStr,
+        allocator: Arc<dyn HostAllocator>,
+    ) -> super::Result<Self> {
+        let builder = Self::builder(entry, app_name)
+            .layers(validation_layers_names)
+            .extensions(extensions_names)
+            .allocator(allocator);
+        // SAFETY: The builder holds owned names and no structure in the `p_next` chain
+        unsafe { builder.build() }
+    }
+
+    /// Returns a builder that allows to set all the parameters of the instance,
+    /// including the ones that [`new`](Self::new) doesn't model
+    pub fn builder<'n>(entry: &'a ash::Entry, app_name: &'n CStr) -> InstanceBuilder<'a, 'n> {
+        InstanceBuilder {
+            entry,
+            app_name,
+            api_version: vk::API_VERSION_1_0,
+            layers: LayerList::new(),
+            extensions: ExtensionList::new(),
+            allocator: None,
+            flags: vk::InstanceCreateFlags::empty(),
+            validation: None,
+            next: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Wraps an instance handle created outside of this crate, e.g. by an OpenXR runtime
+    ///
+    /// `info` must describe the parameters the instance has been created with,
+    /// since they can't be queried from the handle.
+    ///
+    /// When `owned` is `false` the instance is not destroyed when
+    /// the returned value is dropped, whoever created it remains responsible for that
+    /// and must keep it alive until then.
+    ///
+    /// `allocator` is used for the objects created through the returned value,
+    /// and to destroy the instance when `owned` is `true`.
+    ///
+    /// # Safety
+    ///
+    /// `instance` must be a valid instance handle created through `entry`
+    /// with the parameters in `info`.
+    ///
+    /// When `owned` is `true` the instance must not be destroyed by anyone else,
+    /// and must have been created with no allocation callbacks if `allocator` is [`None`],
+    /// or with callbacks compatible with `allocator` otherwise
+    pub unsafe fn from_raw(
+        entry: &'a ash::Entry,
+        instance: vk::Instance,
+        info: InstanceInfo,
+        owned: bool,
+        allocator: Option<Arc<dyn HostAllocator>>,
+    ) -> Self {
+        debug_assert_ne!(instance, vk::Instance::null());
+        Self {
+            instance: ash::Instance::load(entry.static_fn(), instance),
+            entry,
+            allocator: allocator.map(Callbacks::new),
+            info,
+            owned,
+        }
+    }
+
+    /// Returns whether or not the instance handle is destroyed when this value is dropped
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Returns the parameters this instance has been created with
+    pub fn info(&self) -> &InstanceInfo {
+        &self.info
+    }
+
+    /// Returns the `ash` instance, to call the Vulkan functions this crate doesn't wrap
+    ///
+    /// The instance must not be destroyed through the returned value,
+    /// since it's still owned by `self`
+    pub fn vk_instance(&self) -> &ash::Instance {
+        &self.instance
+    }
+
+    /// Returns the allocation callbacks of the instance allocator, if any
+    ///
+    /// The objects created with [`vk_instance`](Self::vk_instance), or with a device
+    /// created from this instance, must use these callbacks to be destroyed along
+    /// with the rest of the objects of this crate
+    pub fn vk_allocator(&self) -> Option<&vk::AllocationCallbacks> {
+        self.allocator.as_ref().map(Callbacks::vk)
+    }
+
+    /// Creates the instance with the parameters collected by the `builder`
+    ///
+    /// # Safety
+    ///
+    /// Same as [`InstanceBuilder::build`]
+    unsafe fn create(builder: InstanceBuilder<'a, '_>) -> super::Result<Self> {
+        let InstanceBuilder {
+            entry,
+            app_name,
+            api_version,
+            layers,
+            mut extensions,
+            allocator,
+            flags,
+            validation,
+            next,
+            marker: _,
+        } = builder;
+
+        // A loader without vkEnumerateInstanceVersion only supports Vulkan 1.0
+        let loader_version = entry
+            .try_enumerate_instance_version()
+            .context(Operation::EnumerateInstanceVersion)?
+            .unwrap_or(vk::API_VERSION_1_0);
+        let api_version = api_version.min(loader_version);
+
+        // The validation features extension is provided by the validation layer itself
+        let validation_ext = vk::ExtValidationFeaturesFn::name();
+        let mut validation = validation.unwrap_or_default();
+        let layer_names: Vec<_> = layers.names().iter().map(CString::as_c_str).collect();
+        if !validation.is_empty()
+            && !available_extensions(entry, &layer_names)?
+                .iter()
+                .any(|prop| name(prop) == validation_ext)
+        {
+            super::warn(
+                "VK_EXT_validation_features is not available, validation features disabled",
+            );
+            validation = ValidationConfig::default();
+        }
+        if !validation.is_empty() {
+            extensions.push(validation_ext);
+        }
+
+        let info = InstanceInfo {
+            api_version,
+            layers: layers.names().to_vec(),
+            extensions: extensions.names().to_vec(),
+            validation,
+        };
+        trace_span!(
+            "create_instance",
+            api_version = info.api_version,
+            layers = ?info.layers,
+            extensions = ?info.extensions,
+        );
+
         let app_info = vk::ApplicationInfo::builder()
             .application_name(app_name)
             .application_version(vk::make_api_version(0, 0, 1, 0))
             .engine_name(cstr!("No Engine"))
             .engine_version(vk::make_api_version(0, 0, 1, 0))
-            .api_version(vk::API_VERSION_1_0)
+            .api_version(info.api_version)
             .build();
 
-        #[cfg(debug_assertions)]
+        #[cfg(all(debug_assertions, feature = "debug-utils"))]
         let mut dbg_utils_info = super::debug_utils::create_info();
 
+        let (extension_ptrs, layer_ptrs) = (extensions.as_ptrs(), layers.as_ptrs());
         let instance_info = vk::InstanceCreateInfo::builder()
+            .flags(flags)
             .application_info(&app_info)
-            .enabled_extension_names(extensions_names)
-            .enabled_layer_names(validation_layers_names);
+            .enabled_extension_names(&extension_ptrs)
+            .enabled_layer_names(&layer_ptrs);
 
-        #[cfg(debug_assertions)]
+        #[cfg(all(debug_assertions, feature = "debug-utils"))]
         let instance_info = instance_info.push_next(&mut dbg_utils_info);
 
-        let instance = entry.create_instance(&instance_info.build(), None)?;
+        let enables = validation.enables();
+        let mut validation_features =
+            vk::ValidationFeaturesEXT::builder().enabled_validation_features(&enables);
+        let instance_info = match enables.is_empty() {
+            true => instance_info,
+            false => instance_info.push_next(&mut validation_features),
+        };
+
+        let mut instance_info = instance_info.build();
+        // Prepend in reverse order to keep the order in which they've been pushed
+        for &next in next.iter().rev() {
+            super::chain::prepend(&mut instance_info.p_next, next);
+        }
+
+        let instance = entry
+            .create_instance(&instance_info, allocator.as_ref().map(Callbacks::vk))
+            .context(Operation::CreateInstance)?;
+
+        Ok(Self {
+            instance,
+            entry,
+            allocator,
+            info,
+            owned: true,
+        })
+    }
+}
+
+/// Builder of an [`Instance`], returned by [`Instance::builder`]
+///
+/// The structures added with [`push_next`](Self::push_next) are borrowed
+/// for the `'n` lifetime, so they are guaranteed to live until the instance is built
+pub struct InstanceBuilder<'a, 'n> {
+    entry: &'a ash::Entry,
+    app_name: &'n CStr,
+    api_version: u32,
+    layers: LayerList,
+    extensions: ExtensionList,
+    allocator: Option<Callbacks>,
+    flags: vk::InstanceCreateFlags,
+    validation: Option<ValidationConfig>,
+    /// The structures to add to the `p_next` chain of the create info
+    next: Vec<*mut vk::BaseOutStructure>,
+    /// Keeps the structures in `next` borrowed
+    marker: PhantomData<&'n mut vk::BaseOutStructure>,
+}
+
+impl<'a, 'n> InstanceBuilder<'a, 'n> {
+    /// Sets the Vulkan API version the application wants to use, by default Vulkan 1.0
+    ///
+    /// The version is lowered to the one supported by the loader, since a Vulkan 1.0 loader
+    /// fails to create instances with any other version.
+    /// The version actually used is reported by [`InstanceInfo::api_version`].
+    pub fn api_version(self, api_version: u32) -> Self {
+        Self {
+            api_version,
+            ..self
+        }
+    }
+
+    /// Sets the names of the layers to enable
+    pub fn layers(self, layers: impl Into<LayerList>) -> Self {
+        Self {
+            layers: layers.into(),
+            ..self
+        }
+    }
+
+    /// Sets the names of the extensions to enable
+    pub fn extensions(self, extensions: impl Into<ExtensionList>) -> Self {
+        Self {
+            extensions: extensions.into(),
+            ..self
+        }
+    }
+
+    /// Sets the allocator used for the host memory allocations made by the Vulkan implementation,
+    /// check [`Instance::with_allocator`]
+    pub fn allocator(self, allocator: Arc<dyn HostAllocator>) -> Self {
+        Self {
+            allocator: Some(Callbacks::new(allocator)),
+            ..self
+        }
+    }
+
+    /// Sets the flags of the [`vk::InstanceCreateInfo`]
+    pub fn flags(self, flags: vk::InstanceCreateFlags) -> Self {
+        Self { flags, ..self }
+    }
+
+    /// Enables the optional features of the validation layers
+    ///
+    /// The `VK_EXT_validation_features` extension is enabled automatically when available,
+    /// otherwise a warning is reported and the features are left disabled.
+    /// A [`vk::ValidationFeaturesEXT`] must not be added with [`push_next`](Self::push_next)
+    /// along with this.
+    pub fn validation(self, validation: ValidationConfig) -> Self {
+        Self {
+            validation: Some(validation),
+            ..self
+        }
+    }
+
+    /// Adds a structure to the `p_next` chain of the [`vk::InstanceCreateInfo`],
+    /// for the parameters the builder doesn't model
+    pub fn push_next<T: vk::ExtendsInstanceCreateInfo>(mut self, next: &'n mut T) -> Self {
+        self.next.push(next as *mut T as *mut vk::BaseOutStructure);
+        self
+    }
+
+    /// Creates the instance
+    ///
+    /// # Safety
+    ///
+    /// The structures added to the `p_next` chain must be valid
+    pub unsafe fn build(self) -> super::Result<Instance<'a>> {
+        Instance::create(self)
+    }
+}
 
-        Ok(Self { instance, entry })
+/// Returns the instance extensions provided either by the implementation
+/// or by one of the `layers`
+///
+/// Some extensions, like `VK_EXT_validation_features`, are only reported when
+/// enumerating the extensions of the layer that provides them.
+/// An extension provided by more than one of them is listed once,
+/// with the highest spec version reported.
+pub fn available_extensions(
+    entry: &ash::Entry,
+    layers: &[&CStr],
+) -> super::Result<Vec<vk::ExtensionProperties>> {
+    let layers = layers.iter().map(|&l| Some(l));
+    let lists = std::iter::once(None)
+        .chain(layers)
+        .map(|layer| {
+            entry
+                .enumerate_instance_extension_properties(layer)
+                .context(Operation::EnumerateInstanceExtensions)
+        })
+        .collect::<super::Result<Vec<_>>>()?;
+    Ok(merge_extensions(lists))
+}
+
+/// Merges the extensions reported by the implementation and the layers,
+/// keeping the first occurrence of each one with the highest spec version
+fn merge_extensions(
+    lists: impl IntoIterator<Item = Vec<vk::ExtensionProperties>>,
+) -> Vec<vk::ExtensionProperties> {
+    let mut available: Vec<vk::ExtensionProperties> = vec![];
+    for prop in lists.into_iter().flatten() {
+        match available.iter_mut().find(|p| name(p) == name(&prop)) {
+            Some(p) => p.spec_version = p.spec_version.max(prop.spec_version),
+            None => available.push(prop),
+        }
+    }
+    available
+}
+
+/// Returns the name of the extension described by `prop`
+fn name(prop: &vk::ExtensionProperties) -> &CStr {
+    // SAFETY: The name is a null terminated string written by the Vulkan driver
+    unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) }
+}
+
+impl super::RawHandle for Instance<'_> {
+    type Handle = vk::Instance;
+
+    fn raw(&self) -> vk::Instance {
+        self.instance.handle()
     }
 }
 
 impl Drop for Instance<'_> {
     fn drop(&mut self) {
+        // An instance that isn't owned is destroyed by whoever created it
+        if !self.owned {
+            return;
+        }
         unsafe {
-            self.instance.destroy_instance(None);
+            let allocator = self.allocator.as_ref().map(Callbacks::vk);
+            self.instance.destroy_instance(allocator);
         }
     }
 }
@@ -77,6 +466,29 @@ pub(super) mod pvt {
 
         /// Returns a reference to the underlying [`ash::Entry`]
         fn vk_entry(&self) -> &ash::Entry;
+
+        /// Returns the allocation callbacks to use when creating or destroying Vulkan objects
+        fn vk_allocator(&self) -> Option<&ash::vk::AllocationCallbacks>;
+
+        /// Returns the parameters the underlying [`ash::Instance`] has been created with
+        fn vk_info(&self) -> &super::InstanceInfo;
+
+        /// Returns the backend through which the physical devices are queried
+        /// and the logical devices are created
+        fn vk_backend(&self) -> &dyn crate::backend::Backend {
+            self.vk_instance()
+        }
+
+        /// Returns the surface held along with the instance, if any,
+        /// and the backend through which it's queried
+        ///
+        /// This allows to query the presentation support where the holder
+        /// is not known to be a [`vku::SurfaceHolder`](crate::SurfaceHolder)
+        fn vk_presentation_surface(
+            &self,
+        ) -> Option<(&dyn crate::backend::SurfaceBackend, ash::vk::SurfaceKHR)> {
+            None
+        }
     }
 }
 
@@ -94,6 +506,14 @@ impl pvt::InstanceHolder for Instance<'_> {
     fn vk_entry(&self) -> &ash::Entry {
         self.entry
     }
+
+    fn vk_allocator(&self) -> Option<&vk::AllocationCallbacks> {
+        self.allocator.as_ref().map(Callbacks::vk)
+    }
+
+    fn vk_info(&self) -> &InstanceInfo {
+        &self.info
+    }
 }
 
 /// Implements the [`InstanceHolder`] in a transitive way by defining the methods
@@ -105,14 +525,14 @@ impl pvt::InstanceHolder for Instance<'_> {
 /// # Example
 ///
 /// Derive the trait on a wrapper type
-/// ```
+/// ```text
 /// struct InstanceWrapper<I: InstanceHolder>(I);
 ///
 /// derive_instance_holder!(InstanceWrapper<I> = 0: I);
 /// ```
 ///
 /// Derive the trait on a wrapper type that has additional generics
-/// ```
+/// ```text
 /// struct InstanceRefWrapper<'a, I: InstanceHolder>(&'a I);
 ///
 /// derive_instance_holder!(
@@ -135,6 +555,93 @@ macro_rules! derive_instance_holder {
             fn vk_entry(&self) -> &ash::Entry {
                 self.$field.vk_entry()
             }
+
+            fn vk_allocator(&self) -> Option<&ash::vk::AllocationCallbacks> {
+                self.$field.vk_allocator()
+            }
+
+            fn vk_info(&self) -> &$crate::instance::InstanceInfo {
+                self.$field.vk_info()
+            }
+
+            fn vk_backend(&self) -> &dyn $crate::backend::Backend {
+                self.$field.vk_backend()
+            }
+
+            fn vk_presentation_surface(
+                &self,
+            ) -> Option<(&dyn $crate::backend::SurfaceBackend, ash::vk::SurfaceKHR)> {
+                self.$field.vk_presentation_surface()
+            }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ext(name: &CStr, spec_version: u32) -> vk::ExtensionProperties {
+        let mut prop = vk::ExtensionProperties {
+            spec_version,
+            ..Default::default()
+        };
+        for (dst, &src) in prop.extension_name.iter_mut().zip(name.to_bytes()) {
+            *dst = src as raw::c_char;
+        }
+        prop
+    }
+
+    fn names_and_versions(props: &[vk::ExtensionProperties]) -> Vec<(&CStr, u32)> {
+        props.iter().map(|p| (name(p), p.spec_version)).collect()
+    }
+
+    #[test]
+    fn merge_without_duplicates() {
+        let merged = merge_extensions([
+            vec![ext(cstr!(VK_KHR_surface), 25)],
+            vec![],
+            vec![ext(cstr!(VK_EXT_debug_utils), 2)],
+        ]);
+        assert_eq!(
+            names_and_versions(&merged),
+            [(cstr!(VK_KHR_surface), 25), (cstr!(VK_EXT_debug_utils), 2)]
+        );
+    }
+
+    #[test]
+    fn merge_keeps_highest_version() {
+        let merged = merge_extensions([
+            vec![
+                ext(cstr!(VK_EXT_debug_utils), 1),
+                ext(cstr!(VK_KHR_surface), 25),
+            ],
+            vec![ext(cstr!(VK_EXT_debug_utils), 2)],
+            vec![
+                ext(cstr!(VK_EXT_debug_utils), 1),
+                ext(cstr!(VK_KHR_surface), 24),
+            ],
+        ]);
+        assert_eq!(
+            names_and_versions(&merged),
+            [(cstr!(VK_EXT_debug_utils), 2), (cstr!(VK_KHR_surface), 25)]
+        );
+    }
+
+    #[test]
+    fn merge_duplicates_in_the_same_list() {
+        let merged = merge_extensions([vec![
+            ext(cstr!(VK_EXT_validation_features), 5),
+            ext(cstr!(VK_EXT_validation_features), 6),
+        ]]);
+        assert_eq!(
+            names_and_versions(&merged),
+            [(cstr!(VK_EXT_validation_features), 6)]
+        );
+    }
+
+    #[test]
+    fn merge_nothing() {
+        assert!(merge_extensions(Vec::new()).is_empty());
+    }
+}
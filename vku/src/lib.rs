@@ -1,25 +1,93 @@
+/// Enters a [`tracing`](https://docs.rs/tracing) span that lasts until the end of the
+/// current block when the `tracing` feature is enabled, expands to nothing otherwise
+///
+/// Accepts the same arguments as `tracing::info_span!`
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($($args)*).entered();
+    };
+}
+
+mod chain;
+
+mod backend;
+
+/// Reports a warning generated by this crate through the same channel
+/// used for the debug messages
+fn warn(msg: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!("{msg}");
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        use std::io::Write;
+        let _ = writeln!(std::io::stderr().lock(), "[ vku ] {msg}");
+    }
+}
+
 mod result;
 pub use result::*;
 
+pub mod raw_handle;
+pub use raw_handle::RawHandle;
+
+pub mod allocator;
+pub use allocator::{CountingAllocator, HostAllocator};
+
+pub mod extension_list;
+pub use extension_list::{ExtensionList, LayerList};
+
 #[macro_use]
 pub mod instance;
-pub use instance::{Instance, InstanceHolder};
+pub use instance::{Instance, InstanceBuilder, InstanceHolder, InstanceInfo};
 
+#[cfg(feature = "surface")]
 #[macro_use]
 pub mod surface;
+#[cfg(feature = "surface")]
 pub use surface::{Surface, SurfaceHolder};
 
+#[cfg(any(feature = "rwh_05", feature = "rwh_06"))]
+pub mod window_handle;
+
+pub mod validation;
+pub use validation::ValidationConfig;
+
+#[cfg(feature = "debug-utils")]
 pub mod debug_utils;
+#[cfg(feature = "debug-utils")]
 pub use debug_utils::DebugUtils;
 
+pub mod memory;
+pub use memory::{HeapBudget, MemoryClass, MemoryPlacement};
+
+pub mod selection;
+pub use selection::{
+    DeviceCriteria, DeviceDescription, DeviceSelector, PresentCandidate, SwapchainPreferences,
+};
+
+pub mod queue;
+pub use queue::Queue;
+
 pub mod queue_family;
 pub use queue_family::QueueFamilyInfo;
 
+pub mod capabilities;
+pub use capabilities::{DeviceCapabilities, DeviceCapabilitiesResolved};
+
 pub mod physical_dev;
-pub use physical_dev::{PhysicalDevList, PhysicalDevRef};
+pub use physical_dev::{DeviceGroup, PhysicalDevList, PhysicalDevRef};
 
 pub mod logical_dev;
-pub use logical_dev::{DeviceHolder, LogicalDev};
+pub use logical_dev::{DeviceHolder, DeviceInfo, LogicalDev};
 
 pub mod swapchain;
+#[cfg(feature = "surface")]
 pub use swapchain::Swapchain;
+
+pub mod pacing;
+pub use pacing::{FramePacer, RefreshRateSource};
+
+pub mod report;
+pub use report::EnvironmentReport;
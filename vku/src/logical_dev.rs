@@ -1,42 +1,347 @@
+use std::ffi::{CStr, CString};
+
 use ash::vk;
 
+use super::{ErrorContext, Operation};
+
+/// The parameters a Vulkan logical device has been created with
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    /// The physical device from which the logical device has been created
+    pub physical_device: vk::PhysicalDevice,
+    /// The queue families for which queues have been created
+    pub queue_families: Vec<super::QueueFamilyInfo>,
+    /// The names of the enabled device extensions
+    pub extensions: Vec<CString>,
+}
+
+impl DeviceInfo {
+    /// Returns whether or not the device extension `name` has been enabled
+    pub fn has_extension(&self, name: &CStr) -> bool {
+        self.extensions.iter().any(|ext| ext.as_c_str() == name)
+    }
+}
+
 /// A wrapper around all the necessary state needed to hold a Vulkan logical device.
 ///
 /// A Vulkan logical device is a connection to a physical device which specifies a subeset of
 /// the capabilities of that physical device that it needs to perform further operations
+///
+/// The device is destroyed when dropped without waiting for it, so the work submitted
+/// to its queues must have completed by then
 pub struct LogicalDev<I: super::InstanceHolder> {
     /// The instance which this logical device belongs to
     instance: I,
     /// The actual Vulkan device handle
     device: ash::Device,
+    /// The parameters this device has been created with
+    info: DeviceInfo,
+    /// The queues created along with the device
+    queues: Vec<super::Queue>,
+    /// How the features promoted to core are provided, considering the enabled extensions
+    resolved: super::DeviceCapabilitiesResolved,
+    /// Whether or not the device handle must be destroyed when this value is dropped
+    owned: bool,
 }
 
 impl<I: super::InstanceHolder> LogicalDev<I> {
-    pub(super) unsafe fn new(instance: I, device: ash::Device) -> Self {
-        Self { instance, device }
+    /// Wraps the device handle and retrieves the queues created along with it
+    ///
+    /// If retrieving the queues fails and the device is `owned` it's destroyed
+    ///
+    /// # Safety
+    ///
+    /// `device` must have been created from `info.physical_device` with the queues
+    /// described by `info.queue_families`
+    pub(super) unsafe fn new(
+        instance: I,
+        device: ash::Device,
+        info: DeviceInfo,
+        owned: bool,
+    ) -> super::Result<Self> {
+        match Self::collect_queues(&instance, &device, &info) {
+            Ok(queues) => Ok(Self::wrap(instance, device, info, queues, owned)),
+            Err(err) => {
+                if owned {
+                    let backend = instance.vk_backend();
+                    backend.destroy_device(&device, instance.vk_allocator());
+                }
+                Err(err)
+            }
+        }
     }
 
-    /// Returns an handle to the selected Vulkan queue
+    /// Wraps a device handle created outside of this crate,
+    /// e.g. by an OpenXR runtime
+    ///
+    /// When `owned` is `false` the device is not destroyed when
+    /// the returned value is dropped, whoever created it remains responsible for that
+    /// and must keep it alive until then.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::InvalidQueueInfos`](super::Error::InvalidQueueInfos) if `info.queue_families`
+    /// is not valid for the physical device, or the error of the queries made to retrieve
+    /// the queues. The device is never destroyed on error, even if `owned` is `true`,
+    /// so it remains up to the caller.
     ///
     /// # Safety
     ///
-    /// `queue_index` must be smaller or equal to the number of queues created for that
-    /// family.
+    /// `device` must be a valid device handle created from `instance` and `info.physical_device`,
+    /// with the queues described by `info.queue_families` and the extensions in `info.extensions`.
     ///
-    /// `queue_family_index` must be one of the indices provided to `new`
-    unsafe fn get_queue(&self, queue_family_index: u32, queue_index: u32) -> vk::Queue {
+    /// When `owned` is `true` the device must not be destroyed by anyone else, and since
+    /// it's destroyed with the allocation callbacks of `instance`, it must have been created
+    /// with callbacks compatible with those (or with none if the instance has no allocator)
+    pub unsafe fn from_raw(
+        instance: I,
+        device: vk::Device,
+        info: DeviceInfo,
+        owned: bool,
+    ) -> super::Result<Self> {
+        debug_assert_ne!(device, vk::Device::null());
+        debug_assert_ne!(info.physical_device, vk::PhysicalDevice::null());
+        // The info comes from the caller, so check it before asking for the queues
+        let families = instance
+            .vk_backend()
+            .get_physical_device_queue_family_properties(info.physical_device);
+        super::queue_family::validate(&info.queue_families, &families)
+            .map_err(|reason| super::Error::InvalidQueueInfos { reason })?;
+
+        let device = ash::Device::load(instance.vk_instance().fp_v1_0(), device);
+        // Take the ownership only after everything has been queried,
+        // so that the device is left to the caller on error
+        let queues = Self::collect_queues(&instance, &device, &info)?;
+        Ok(Self::wrap(instance, device, info, queues, owned))
+    }
+
+    /// Builds the value from its parts, resolving the features promoted to core
+    fn wrap(
+        instance: I,
+        device: ash::Device,
+        info: DeviceInfo,
+        queues: Vec<super::Queue>,
+        owned: bool,
+    ) -> Self {
+        let device_version = unsafe {
+            instance
+                .vk_backend()
+                .get_physical_device_properties(info.physical_device)
+        }
+        .api_version;
+        let api_version = instance.vk_info().api_version.min(device_version);
+        let resolved = super::DeviceCapabilitiesResolved::resolve(api_version, |name| {
+            info.has_extension(name)
+        });
+        Self {
+            instance,
+            device,
+            info,
+            queues,
+            resolved,
+            owned,
+        }
+    }
+
+    /// Retrieves the queues created along with `device`, in the order of `info.queue_families`
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new)
+    unsafe fn collect_queues(
+        instance: &I,
+        device: &ash::Device,
+        info: &DeviceInfo,
+    ) -> super::Result<Vec<super::Queue>> {
+        let phydev = info.physical_device;
+        let families = instance
+            .vk_backend()
+            .get_physical_device_queue_family_properties(phydev);
+        let mut queues = Vec::new();
+        for family in &info.queue_families {
+            let flags = families
+                .get(family.index as usize)
+                .ok_or(super::Error::InvalidQueueInfos {
+                    reason: super::queue_family::InvalidQueueInfos::FamilyIndex {
+                        index: family.index,
+                        count: families.len() as u32,
+                    },
+                })?
+                .queue_flags;
+            let supports_present = match instance.vk_presentation_surface() {
+                Some((fns, surface)) => fns
+                    .get_physical_device_surface_support(phydev, family.index, surface)
+                    .context_of(Operation::GetSurfaceSupport, phydev)?,
+                None => false,
+            };
+            queues.extend((0..family.priorities.len() as u32).map(|index| {
+                super::Queue {
+                    handle: instance
+                        .vk_backend()
+                        .get_device_queue(device, family.index, index),
+                    family_index: family.index,
+                    index,
+                    flags,
+                    supports_present,
+                }
+            }));
+        }
+        Ok(queues)
+    }
+
+    /// Returns whether or not the device handle is destroyed when this value is dropped
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Returns the instance this device has been created from
+    pub fn instance(&self) -> &I {
+        &self.instance
+    }
+
+    /// Returns the parameters this device has been created with
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// Returns how the features promoted to core are provided by this device,
+    /// computed when it has been created
+    pub fn resolved(&self) -> &super::DeviceCapabilitiesResolved {
+        &self.resolved
+    }
+
+    /// Returns the `ash` device, to call the Vulkan functions this crate doesn't wrap
+    ///
+    /// The device must not be destroyed through the returned value,
+    /// since it's still owned by `self`
+    pub fn vk_device(&self) -> &ash::Device {
+        &self.device
+    }
+
+    /// Returns the queues created along with the device,
+    /// in the same order as the queue families they have been requested for
+    pub fn queues(&self) -> &[super::Queue] {
+        &self.queues
+    }
+
+    /// Returns the first queue that supports all the operations in `flags`
+    pub fn queue_with(&self, flags: vk::QueueFlags) -> Option<&super::Queue> {
+        self.queues.iter().find(|queue| queue.supports(flags))
+    }
+
+    /// Returns the first queue that can present to the surface
+    pub fn present_queue(&self) -> Option<&super::Queue> {
+        self.queues.iter().find(|queue| queue.supports_present)
+    }
+
+    /// Creates a command pool for the family of `queue`
+    ///
+    /// The command buffers allocated from the pool can only be submitted to
+    /// the queues of that family, and the pool must be destroyed
+    /// with [`vk_device`](Self::vk_device) before the device
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `queue` doesn't belong to this device
+    pub fn create_command_pool(
+        &self,
+        queue: &super::Queue,
+        flags: vk::CommandPoolCreateFlags,
+    ) -> super::Result<vk::CommandPool> {
+        debug_assert!(self.queues.contains(queue));
+        let create_info = vk::CommandPoolCreateInfo::builder()
+            .flags(flags)
+            .queue_family_index(queue.family_index);
+        unsafe {
+            self.device
+                .create_command_pool(&create_info, self.instance.vk_allocator())
+        }
+        .context_of(Operation::CreateCommandPool, self.device.handle())
+    }
+
+    /// Creates a shader module from the SPIR-V `code`
+    ///
+    /// When debug printf is enabled in the [`ValidationConfig`](super::ValidationConfig)
+    /// of the instance, a warning is reported if the shader has not been compiled with it,
+    /// check [`uses_debug_printf`](super::validation::uses_debug_printf).
+    ///
+    /// The module must be destroyed with [`vk_device`](Self::vk_device) before the device
+    ///
+    /// # Safety
+    ///
+    /// `code` must be a valid SPIR-V module for the capabilities and the extensions
+    /// enabled on the device
+    pub unsafe fn create_shader_module(&self, code: &[u32]) -> super::Result<vk::ShaderModule> {
+        if self.instance.vk_info().validation.debug_printf
+            && !super::validation::uses_debug_printf(code)
+        {
+            super::warn(&format!(
+                "debug printf is enabled but the shader module doesn't import {}, \
+                its debugPrintfEXT calls produce no output",
+                super::validation::DEBUG_PRINTF_INSTRUCTION_SET
+            ));
+        }
+        let create_info = vk::ShaderModuleCreateInfo::builder().code(code);
         self.device
-            .get_device_queue(queue_family_index, queue_index)
+            .create_shader_module(&create_info, self.instance.vk_allocator())
+            .context_of(Operation::CreateShaderModule, self.device.handle())
+    }
+
+    /// Submits the work in `submits` to `queue`,
+    /// `fence` is signaled once all of it has completed and can be null
+    ///
+    /// `required` are the operations recorded in the submitted command buffers
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `queue` doesn't belong to this device
+    /// or doesn't support all the operations in `required`
+    ///
+    /// # Safety
+    ///
+    /// Same as [`vkQueueSubmit`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkQueueSubmit.html),
+    /// in particular the command buffers must have been allocated from a pool
+    /// of the family of `queue` and the queue must not be used from other threads meanwhile
+    pub unsafe fn submit(
+        &self,
+        queue: &super::Queue,
+        required: vk::QueueFlags,
+        submits: &[vk::SubmitInfo],
+        fence: vk::Fence,
+    ) -> super::Result<()> {
+        debug_assert!(self.queues.contains(queue));
+        debug_assert!(
+            queue.supports(required),
+            "queue family {} doesn't support {required:?}",
+            queue.family_index
+        );
+        self.device
+            .queue_submit(queue.handle, submits, fence)
+            .context_of(Operation::QueueSubmit, queue.handle)
     }
 }
 
 impl<I: super::InstanceHolder> Drop for LogicalDev<I> {
     fn drop(&mut self) {
-        unsafe { self.device.destroy_device(None) }
+        // A device that isn't owned is destroyed by whoever created it
+        if !self.owned {
+            return;
+        }
+        let backend = self.instance.vk_backend();
+        unsafe { backend.destroy_device(&self.device, self.instance.vk_allocator()) }
+    }
+}
+
+impl<I: super::InstanceHolder> super::RawHandle for LogicalDev<I> {
+    type Handle = vk::Device;
+
+    fn raw(&self) -> vk::Device {
+        self.device.handle()
     }
 }
 
 derive_instance_holder!(LogicalDev<I> = instance: I);
+#[cfg(feature = "surface")]
 derive_surface_holder!(LogicalDev<I> = instance: I);
 
 /// Private definitions available only to the [vku](super) module
@@ -48,6 +353,9 @@ pub(super) mod pvt {
     pub trait DeviceHolder {
         /// Returns a reference to the underlying [`vk::Device`](ash::vk::Device)
         fn vk_device(&self) -> &ash::Device;
+
+        /// Returns the parameters the underlying [`ash::Device`] has been created with
+        fn vk_device_info(&self) -> &super::DeviceInfo;
     }
 }
 
@@ -61,6 +369,10 @@ impl<I: super::InstanceHolder> pvt::DeviceHolder for LogicalDev<I> {
     fn vk_device(&self) -> &ash::Device {
         &self.device
     }
+
+    fn vk_device_info(&self) -> &DeviceInfo {
+        &self.info
+    }
 }
 
 /// Implements the [`DeviceHolder`] in a transitive way by defining the methods
@@ -72,14 +384,14 @@ impl<I: super::InstanceHolder> pvt::DeviceHolder for LogicalDev<I> {
 /// # Example
 ///
 /// Derive the trait on a wrapper type
-/// ```
+/// ```text
 /// struct DeviceWrapper<I: DeviceHolder>(I);
 ///
-/// derive_instance_holder!(DeviceWrapper<I> = 0: I);
+/// derive_device_holder!(DeviceWrapper<I> = 0: I);
 /// ```
 ///
 /// Derive the trait on a wrapper type that has additional generics
-/// ```
+/// ```text
 /// struct DeviceWrapper<'a, I: DeviceHolder>(&'a I);
 ///
 /// derive_device_holder!(
@@ -87,17 +399,75 @@ impl<I: super::InstanceHolder> pvt::DeviceHolder for LogicalDev<I> {
 ///     DeviceWrapper<'a, I> = 0: I
 /// );
 /// ```
+#[allow(unused_macros)]
 macro_rules! derive_device_holder {
     ( $( #[generics( $( $generics:tt )* )] )? $self:ty = $field:tt : $generic:ident) => {
         impl<
             // Additional generics, note the comma before closing the optional block
             $( $( $generics )* , )?
-            // InstanceHodler generic
+            // DeviceHolder generic
             $generic : $crate::DeviceHolder
-        > $crate::instance::pvt::DeviceHolder for $self {
+        > $crate::logical_dev::pvt::DeviceHolder for $self {
             fn vk_device(&self) -> &ash::Device {
                 self.$field.vk_device()
             }
+
+            fn vk_device_info(&self) -> &$crate::logical_dev::DeviceInfo {
+                self.$field.vk_device_info()
+            }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock;
+
+    fn info(families: &[u32]) -> DeviceInfo {
+        DeviceInfo {
+            physical_device: mock::Instance::device_handle(0),
+            queue_families: families
+                .iter()
+                .map(|&index| super::super::QueueFamilyInfo {
+                    index,
+                    priorities: vec![1.0],
+                })
+                .collect(),
+            extensions: vec![],
+        }
+    }
+
+    fn instance() -> mock::Instance {
+        let device = mock::Device::new("gpu", vk::PhysicalDeviceType::DISCRETE_GPU, 0);
+        mock::Instance::new(vec![device])
+    }
+
+    #[test]
+    fn from_raw_rejects_invalid_families() {
+        let raw = vk::Handle::from_raw(1);
+        let Err(err) = (unsafe { LogicalDev::from_raw(instance(), raw, info(&[2]), true) }) else {
+            panic!("the device has been wrapped");
+        };
+        assert_eq!(
+            err,
+            super::super::Error::InvalidQueueInfos {
+                reason: super::super::queue_family::InvalidQueueInfos::FamilyIndex {
+                    index: 2,
+                    count: 1
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn from_raw_retrieves_the_queues() {
+        let raw = vk::Handle::from_raw(1);
+        let dev = unsafe { LogicalDev::from_raw(instance(), raw, info(&[0]), false) }.unwrap();
+        assert!(!dev.is_owned());
+        assert_eq!(dev.queues().len(), 1);
+        assert!(dev.queue_with(vk::QueueFlags::GRAPHICS).is_some());
+        // There is no surface held along with the instance
+        assert_eq!(dev.present_queue(), None);
+    }
+}
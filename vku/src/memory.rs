@@ -0,0 +1,294 @@
+use ash::vk;
+
+/// The memory budget of a memory heap of a physical device
+///
+/// Returned by [`vku::PhysicalDevRef::memory_budget`](super::PhysicalDevRef::memory_budget)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapBudget {
+    /// Attribute flags of the heap
+    pub flags: vk::MemoryHeapFlags,
+    /// Total size of the heap in bytes
+    pub size: vk::DeviceSize,
+    /// Estimate of how many bytes the process can allocate from the heap
+    /// before allocations may fail or cause performance degradation
+    ///
+    /// When the budget can't be queried this is the size of the heap
+    pub budget: vk::DeviceSize,
+    /// Estimate of how many bytes are currently allocated by the process from the heap,
+    /// [`None`] when the budget can't be queried
+    pub usage: Option<vk::DeviceSize>,
+}
+
+impl HeapBudget {
+    /// Returns how much of the budget is in use as a ratio between `0.0` and `1.0`,
+    /// or [`None`] if the usage is unknown
+    ///
+    /// Applications can use this value to decide when to start evicting resources
+    pub fn pressure(&self) -> Option<f32> {
+        let usage = self.usage?;
+        match self.budget {
+            0 => Some(1.0),
+            budget => Some((usage as f64 / budget as f64).min(1.0) as f32),
+        }
+    }
+}
+
+/// Heaps up to this size are considered to be the legacy 256 MiB BAR window,
+/// only bigger ones (resizable BAR) are worth placing resources into
+pub const MIN_REBAR_HEAP_SIZE: vk::DeviceSize = 256 * 1024 * 1024;
+
+/// The kind of a memory type, based on where it's located and who can access it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryClass {
+    /// Memory only accessible by the device
+    DeviceLocal,
+    /// Memory on the host that the device accesses through the bus
+    HostVisible,
+    /// Device memory mapped on the host through the PCIe BAR
+    Bar,
+    /// Memory that can't be accessed by neither the host nor the device directly
+    /// (e.g. lazily allocated or protected memory)
+    Other,
+}
+
+impl MemoryClass {
+    /// Classifies a memory type based on its property flags
+    pub fn of(flags: vk::MemoryPropertyFlags) -> Self {
+        use vk::MemoryPropertyFlags as Mem;
+        if flags.intersects(Mem::LAZILY_ALLOCATED | Mem::PROTECTED) {
+            return Self::Other;
+        }
+        match (
+            flags.contains(Mem::DEVICE_LOCAL),
+            flags.contains(Mem::HOST_VISIBLE),
+        ) {
+            (true, true) => Self::Bar,
+            (true, false) => Self::DeviceLocal,
+            (false, true) => Self::HostVisible,
+            (false, false) => Self::Other,
+        }
+    }
+}
+
+/// Where the memory of a resource should be placed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryPlacement {
+    /// Device local memory writable by the host, useful for data updated frequently.
+    ///
+    /// Falls back to [`HostOnly`](Self::HostOnly) when there is no large enough BAR heap
+    PreferBar,
+    /// Device local memory only, its contents must be uploaded through a staging buffer
+    DeviceOnlyWithStaging,
+    /// Host visible memory
+    HostOnly,
+}
+
+/// Returns the index of the memory type to use for a resource with the provided placement
+///
+/// `type_bits` is the `memory_type_bits` of the [`vk::MemoryRequirements`] of the resource.
+///
+/// When the budget of the heaps is provided, heaps whose usage already exceeds the budget
+/// are not considered for the BAR placement.
+///
+/// Returns [`None`] if no memory type is compatible with the resource
+pub fn select_memory_type(
+    props: &vk::PhysicalDeviceMemoryProperties,
+    type_bits: u32,
+    placement: MemoryPlacement,
+    budget: Option<&[HeapBudget]>,
+) -> Option<u32> {
+    let types = &props.memory_types[..props.memory_type_count as usize];
+    let candidates = || {
+        types
+            .iter()
+            .enumerate()
+            .filter(move |&(i, _)| type_bits & (1 << i) != 0)
+            .map(|(i, ty)| (i as u32, ty))
+    };
+    let find = |class: MemoryClass| {
+        candidates()
+            .find(|(_, ty)| MemoryClass::of(ty.property_flags) == class)
+            .map(|(i, _)| i)
+    };
+
+    let host_coherent = || {
+        candidates()
+            .filter(|(_, ty)| MemoryClass::of(ty.property_flags) == MemoryClass::HostVisible)
+            .find(|(_, ty)| {
+                let flags = ty.property_flags;
+                flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+            })
+            .map(|(i, _)| i)
+            .or_else(|| find(MemoryClass::HostVisible))
+    };
+
+    match placement {
+        MemoryPlacement::PreferBar => candidates()
+            .filter(|(_, ty)| MemoryClass::of(ty.property_flags) == MemoryClass::Bar)
+            .find(|(_, ty)| {
+                let heap = ty.heap_index as usize;
+                let over_budget = budget
+                    .and_then(|b| b.get(heap))
+                    .is_some_and(|b| b.pressure() == Some(1.0));
+                props.memory_heaps[heap].size > MIN_REBAR_HEAP_SIZE && !over_budget
+            })
+            .map(|(i, _)| i)
+            .or_else(host_coherent)
+            .or_else(|| find(MemoryClass::Bar)),
+        MemoryPlacement::DeviceOnlyWithStaging => find(MemoryClass::DeviceLocal)
+            // Integrated GPUs usually only have memory that is both local and visible
+            .or_else(|| find(MemoryClass::Bar)),
+        MemoryPlacement::HostOnly => host_coherent().or_else(|| find(MemoryClass::Bar)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vk::MemoryHeapFlags as Heap;
+    use vk::MemoryPropertyFlags as Mem;
+
+    const GIB: vk::DeviceSize = 1024 * 1024 * 1024;
+
+    /// Builds the memory properties from the `(flags, size)` of the heaps
+    /// and the `(flags, heap_index)` of the types
+    fn props(
+        heaps: &[(Heap, vk::DeviceSize)],
+        types: &[(Mem, u32)],
+    ) -> vk::PhysicalDeviceMemoryProperties {
+        let mut props = vk::PhysicalDeviceMemoryProperties {
+            memory_heap_count: heaps.len() as u32,
+            memory_type_count: types.len() as u32,
+            ..Default::default()
+        };
+        for (heap, &(flags, size)) in props.memory_heaps.iter_mut().zip(heaps) {
+            *heap = vk::MemoryHeap { size, flags };
+        }
+        for (ty, &(property_flags, heap_index)) in props.memory_types.iter_mut().zip(types) {
+            *ty = vk::MemoryType {
+                property_flags,
+                heap_index,
+            };
+        }
+        props
+    }
+
+    /// A discrete GPU with the BAR heap of size `bar`
+    fn discrete(bar: vk::DeviceSize) -> vk::PhysicalDeviceMemoryProperties {
+        let visible = Mem::HOST_VISIBLE | Mem::HOST_COHERENT;
+        props(
+            &[
+                (Heap::DEVICE_LOCAL, 8 * GIB),
+                (Heap::empty(), 16 * GIB),
+                (Heap::DEVICE_LOCAL, bar),
+            ],
+            &[
+                (Mem::DEVICE_LOCAL, 0),
+                (visible, 1),
+                (visible | Mem::HOST_CACHED, 1),
+                (Mem::DEVICE_LOCAL | visible, 2),
+            ],
+        )
+    }
+
+    /// An integrated GPU sharing the system memory
+    fn integrated() -> vk::PhysicalDeviceMemoryProperties {
+        let visible = Mem::DEVICE_LOCAL | Mem::HOST_VISIBLE | Mem::HOST_COHERENT;
+        props(
+            &[(Heap::DEVICE_LOCAL, 8 * GIB)],
+            &[
+                (Mem::DEVICE_LOCAL, 0),
+                (visible, 0),
+                (visible | Mem::HOST_CACHED, 0),
+            ],
+        )
+    }
+
+    /// A software implementation (e.g. lavapipe) with a single type for everything
+    fn software() -> vk::PhysicalDeviceMemoryProperties {
+        let all = Mem::DEVICE_LOCAL | Mem::HOST_VISIBLE | Mem::HOST_COHERENT | Mem::HOST_CACHED;
+        props(&[(Heap::DEVICE_LOCAL, 2 * GIB)], &[(all, 0)])
+    }
+
+    fn select(
+        props: &vk::PhysicalDeviceMemoryProperties,
+        placement: MemoryPlacement,
+    ) -> Option<u32> {
+        select_memory_type(props, u32::MAX, placement, None)
+    }
+
+    #[test]
+    fn discrete_gpu() {
+        let props = discrete(256 * 1024 * 1024);
+        assert_eq!(
+            select(&props, MemoryPlacement::DeviceOnlyWithStaging),
+            Some(0)
+        );
+        assert_eq!(select(&props, MemoryPlacement::HostOnly), Some(1));
+        // The legacy BAR window is too small, so host memory is used instead
+        assert_eq!(select(&props, MemoryPlacement::PreferBar), Some(1));
+    }
+
+    #[test]
+    fn discrete_gpu_with_resizable_bar() {
+        let props = discrete(8 * GIB);
+        assert_eq!(select(&props, MemoryPlacement::PreferBar), Some(3));
+        assert_eq!(
+            select(&props, MemoryPlacement::DeviceOnlyWithStaging),
+            Some(0)
+        );
+        assert_eq!(select(&props, MemoryPlacement::HostOnly), Some(1));
+    }
+
+    #[test]
+    fn discrete_gpu_over_budget() {
+        let props = discrete(8 * GIB);
+        let budget: Vec<_> = props.memory_heaps[..3]
+            .iter()
+            .map(|heap| HeapBudget {
+                flags: heap.flags,
+                size: heap.size,
+                budget: heap.size,
+                usage: Some(heap.size),
+            })
+            .collect();
+        let placement = MemoryPlacement::PreferBar;
+        assert_eq!(
+            select_memory_type(&props, u32::MAX, placement, Some(&budget)),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn integrated_gpu() {
+        let props = integrated();
+        assert_eq!(select(&props, MemoryPlacement::PreferBar), Some(1));
+        assert_eq!(
+            select(&props, MemoryPlacement::DeviceOnlyWithStaging),
+            Some(0)
+        );
+        // There is no host only memory, the shared one is used
+        assert_eq!(select(&props, MemoryPlacement::HostOnly), Some(1));
+    }
+
+    #[test]
+    fn software_gpu() {
+        let props = software();
+        assert_eq!(select(&props, MemoryPlacement::PreferBar), Some(0));
+        assert_eq!(
+            select(&props, MemoryPlacement::DeviceOnlyWithStaging),
+            Some(0)
+        );
+        assert_eq!(select(&props, MemoryPlacement::HostOnly), Some(0));
+    }
+
+    #[test]
+    fn type_bits_restrict_the_choice() {
+        let props = discrete(8 * GIB);
+        let placement = MemoryPlacement::DeviceOnlyWithStaging;
+        assert_eq!(select_memory_type(&props, 0b1110, placement, None), Some(3));
+        assert_eq!(select_memory_type(&props, 0b0110, placement, None), None);
+        assert_eq!(select_memory_type(&props, 0, placement, None), None);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ash::vk;
+
+/// How long before the deadline the pacer stops sleeping and starts spinning,
+/// since the OS sleep granularity is usually around a millisecond
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Statistics about the duration of the last frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Average frame time
+    pub avg: Duration,
+    /// 95th percentile of the frame times
+    pub p95: Duration,
+    /// 99th percentile of the frame times
+    pub p99: Duration,
+}
+
+impl FrameStats {
+    /// Computes the statistics of the provided frame times,
+    /// returns [`None`] if there are none
+    pub fn of(times: impl IntoIterator<Item = Duration>) -> Option<Self> {
+        let mut times: Vec<_> = times.into_iter().collect();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort_unstable();
+        let percentile = |p: usize| times[((times.len() * p).div_ceil(100)).max(1) - 1];
+        Some(Self {
+            avg: times.iter().sum::<Duration>() / times.len() as u32,
+            p95: percentile(95),
+            p99: percentile(99),
+        })
+    }
+}
+
+/// Provides the refresh rate of the display the frames are presented on,
+/// e.g. from the video mode of the monitor
+pub trait RefreshRateSource {
+    /// Returns the refresh rate in Hz, or [`None`] if it's not known
+    fn refresh_rate(&self) -> Option<f64>;
+}
+
+/// A fixed refresh rate
+impl RefreshRateSource for f64 {
+    fn refresh_rate(&self) -> Option<f64> {
+        Some(*self)
+    }
+}
+
+impl<T: RefreshRateSource> RefreshRateSource for Option<T> {
+    fn refresh_rate(&self) -> Option<f64> {
+        self.as_ref()?.refresh_rate()
+    }
+}
+
+/// Limits the frame rate to a target and keeps track of the frame times
+pub struct FramePacer {
+    /// The minimum duration of a frame, [`None`] for no limit
+    target: Option<Duration>,
+    /// The refresh period of the display, used instead of `target` when presenting with FIFO
+    refresh: Option<Duration>,
+    /// When the last frame ended
+    last: Option<Instant>,
+    /// The durations of the last frames, the oldest first
+    times: VecDeque<Duration>,
+    /// How many frame times are kept
+    history: usize,
+}
+
+impl FramePacer {
+    /// Creates a pacer that limits the frame rate to `target_fps`, if any,
+    /// and keeps the times of the last `history` frames
+    pub fn new(target_fps: Option<f64>, history: usize) -> Self {
+        Self {
+            target: target_fps.and_then(period),
+            refresh: None,
+            last: None,
+            times: VecDeque::with_capacity(history),
+            history,
+        }
+    }
+
+    /// Changes the target frame rate, [`None`] to remove the limit
+    pub fn set_target_fps(&mut self, target_fps: Option<f64>) {
+        self.target = target_fps.and_then(period);
+    }
+
+    /// Follows the refresh rate reported by `source` when `present_mode` is FIFO
+    /// (or FIFO relaxed), the target frame rate is used otherwise or when the rate is unknown
+    ///
+    /// With FIFO the presentation engine releases an image per refresh,
+    /// so pacing to the refresh rate avoids queueing frames that would only add latency.
+    pub fn sync_to_display(
+        &mut self,
+        present_mode: vk::PresentModeKHR,
+        source: &dyn RefreshRateSource,
+    ) {
+        use vk::PresentModeKHR as M;
+        self.refresh = match present_mode {
+            M::FIFO | M::FIFO_RELAXED => source.refresh_rate().and_then(period),
+            _ => None,
+        };
+    }
+
+    /// Returns the time available for each frame, [`None`] if there is no limit
+    ///
+    /// This is the refresh period of the display when following it,
+    /// check [`sync_to_display`](Self::sync_to_display), the target frame time otherwise
+    pub fn budget(&self) -> Option<Duration> {
+        self.refresh.or(self.target)
+    }
+
+    /// Waits until the target frame time has passed since the end of the last frame,
+    /// then marks the end of the current one and returns its duration
+    ///
+    /// The first call returns [`Duration::ZERO`] since there is no previous frame.
+    pub fn wait(&mut self) -> Duration {
+        if let (Some(last), Some(target)) = (self.last, self.budget()) {
+            let deadline = last + target;
+            if let Some(sleep) = sleep_duration(deadline, Instant::now()) {
+                std::thread::sleep(sleep);
+            }
+            while Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+        }
+        self.end_frame(Instant::now())
+    }
+
+    /// Marks the end of the current frame at `now` and returns its duration,
+    /// recording it in the history
+    fn end_frame(&mut self, now: Instant) -> Duration {
+        let Some(last) = self.last.replace(now) else {
+            return Duration::ZERO;
+        };
+        let frame = now.saturating_duration_since(last);
+
+        if self.history > 0 {
+            if self.times.len() == self.history {
+                self.times.pop_front();
+            }
+            self.times.push_back(frame);
+        }
+        frame
+    }
+
+    /// Returns the statistics of the last frames, or [`None`] if no frame has been recorded
+    pub fn stats(&self) -> Option<FrameStats> {
+        FrameStats::of(self.times.iter().copied())
+    }
+}
+
+/// Returns the duration of a frame at `fps` frames per second,
+/// or [`None`] if it's not a positive rate
+fn period(fps: f64) -> Option<Duration> {
+    (fps > 0.0 && fps.is_finite()).then(|| Duration::from_secs_f64(1.0 / fps))
+}
+
+/// Returns how long to sleep to get close to `deadline` without overshooting it,
+/// the remaining time before it should be spent spinning
+///
+/// Returns [`None`] if the deadline is too close to sleep
+pub fn sleep_duration(deadline: Instant, now: Instant) -> Option<Duration> {
+    deadline
+        .checked_duration_since(now)?
+        .checked_sub(SPIN_THRESHOLD)
+        .filter(|d| !d.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// A display whose refresh rate may not be known
+    struct Display(Option<f64>);
+
+    impl RefreshRateSource for Display {
+        fn refresh_rate(&self) -> Option<f64> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn period_of_frame_rates() {
+        assert_eq!(period(1.0), Some(Duration::from_secs(1)));
+        assert_eq!(period(50.0), Some(20 * MS));
+        assert_eq!(period(60.0), Some(Duration::from_nanos(16_666_667)));
+        assert_eq!(period(144.0), Some(Duration::from_nanos(6_944_444)));
+        for fps in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(period(fps), None, "{fps}");
+        }
+    }
+
+    #[test]
+    fn budget_follows_fifo_refresh() {
+        let mut pacer = FramePacer::new(Some(100.0), 0);
+        assert_eq!(pacer.budget(), Some(10 * MS));
+
+        pacer.sync_to_display(vk::PresentModeKHR::FIFO, &Display(Some(50.0)));
+        assert_eq!(pacer.budget(), Some(20 * MS));
+        pacer.sync_to_display(vk::PresentModeKHR::FIFO_RELAXED, &Display(Some(40.0)));
+        assert_eq!(pacer.budget(), Some(25 * MS));
+
+        // The target is used with the other modes or when the rate is unknown
+        pacer.sync_to_display(vk::PresentModeKHR::MAILBOX, &Display(Some(50.0)));
+        assert_eq!(pacer.budget(), Some(10 * MS));
+        pacer.sync_to_display(vk::PresentModeKHR::FIFO, &Display(None));
+        assert_eq!(pacer.budget(), Some(10 * MS));
+
+        pacer.set_target_fps(None);
+        assert_eq!(pacer.budget(), None);
+        pacer.sync_to_display(vk::PresentModeKHR::FIFO, &Some(50.0));
+        assert_eq!(pacer.budget(), Some(20 * MS));
+    }
+
+    #[test]
+    fn frame_times_with_fake_clock() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(None, 3);
+        assert_eq!(pacer.end_frame(start), Duration::ZERO);
+        assert_eq!(pacer.stats(), None);
+
+        let mut now = start;
+        for ms in [10, 20, 30, 40] {
+            now += ms * MS;
+            assert_eq!(pacer.end_frame(now), ms * MS);
+        }
+        // Only the last 3 frames are kept
+        assert_eq!(
+            pacer.stats(),
+            Some(FrameStats {
+                avg: 30 * MS,
+                p95: 40 * MS,
+                p99: 40 * MS,
+            })
+        );
+    }
+
+    #[test]
+    fn frame_times_without_history() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(None, 0);
+        pacer.end_frame(start);
+        assert_eq!(pacer.end_frame(start + 5 * MS), 5 * MS);
+        assert_eq!(pacer.stats(), None);
+    }
+
+    #[test]
+    fn stats_percentiles() {
+        let stats = FrameStats::of((1..=100).map(|ms| ms * MS)).unwrap();
+        assert_eq!(stats.avg, Duration::from_micros(50_500));
+        assert_eq!(stats.p95, 95 * MS);
+        assert_eq!(stats.p99, 99 * MS);
+
+        let single = FrameStats::of([7 * MS]).unwrap();
+        assert_eq!(
+            (single.avg, single.p95, single.p99),
+            (7 * MS, 7 * MS, 7 * MS)
+        );
+        assert_eq!(FrameStats::of([]), None);
+    }
+
+    #[test]
+    fn sleep_before_deadline() {
+        let now = Instant::now();
+        assert_eq!(sleep_duration(now + 10 * MS, now), Some(9 * MS));
+        assert_eq!(sleep_duration(now + MS, now), None);
+        assert_eq!(sleep_duration(now, now + MS), None);
+    }
+}
@@ -1,7 +1,9 @@
-use std::os::raw::c_char;
+use std::{ffi::CStr, os::raw::c_char};
 
 use ash::{extensions::khr, vk};
 
+use super::{ErrorContext, HeapBudget, Operation};
+
 /// A list of Vulkan physical device handles
 ///
 /// A physical device in Vulkan is a reference to a physical GPU
@@ -11,10 +13,19 @@ use ash::{extensions::khr, vk};
 ///
 /// # Examples
 ///
-/// ```
-/// let list = PhysicalDevList::list(instance)?;
-/// let index = most_suitable(&list);
-/// let logical_device = list.select(index, queue_family_indices)?;
+/// ```no_run
+/// # fn main() -> vku::Result<()> {
+/// # let entry = unsafe { ash::Entry::load() }.expect("Vulkan loader not found");
+/// # let no_names: [&std::ffi::CStr; 0] = [];
+/// let instance = vku::Instance::new(&entry, no_names, no_names, c"app")?;
+/// let list = vku::PhysicalDevList::list(instance)?;
+/// let criteria = vku::DeviceCriteria::default();
+/// let queues = vec![vku::QueueFamilyInfo { index: 0, priorities: vec![1.0] }];
+/// let logical_device =
+///     unsafe { list.select_best_checked(&criteria, |_| Some(queues.clone()), no_names)? }
+///         .expect("no suitable device");
+/// # Ok(())
+/// # }
 /// ```
 pub struct PhysicalDevList<I: super::InstanceHolder> {
     /// The instance from which those devices
@@ -31,31 +42,203 @@ pub struct PhysicalDevRef<'a, I: super::InstanceHolder> {
     pub handle: vk::PhysicalDevice,
 }
 
+/// A set of physical devices that can be used together to create a single logical device
+///
+/// Returned by [`PhysicalDevList::device_groups`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceGroup {
+    /// The indices in the [`PhysicalDevList`] of the devices in the group
+    pub devices: Vec<usize>,
+    /// Whether or not memory allocations can be made on a subset of the devices in the group
+    pub subset_allocation: bool,
+}
+
+/// The functions through which the `vkGetPhysicalDevice*2` queries can be performed
+enum Properties2Fns {
+    /// Core functions of Vulkan 1.1
+    Core,
+    /// Functions of the `VK_KHR_get_physical_device_properties2` instance extension
+    Khr(khr::GetPhysicalDeviceProperties2),
+}
+
 // Cannot derive Clone + Copy due to the unwanted additional trait bound constrains
 // that it adds to the generics, so implement them manually
 
 impl<I: super::InstanceHolder> Clone for PhysicalDevRef<'_, I> {
     fn clone(&self) -> Self {
-        let Self { instance, handle } = *self;
-        Self { instance, handle }
+        *self
     }
 }
 
 impl<I: super::InstanceHolder> Copy for PhysicalDevRef<'_, I> {}
 
+impl<I: super::InstanceHolder> super::RawHandle for PhysicalDevRef<'_, I> {
+    type Handle = vk::PhysicalDevice;
+
+    fn raw(&self) -> vk::PhysicalDevice {
+        self.handle
+    }
+}
+
 impl<I: super::InstanceHolder> PhysicalDevList<I> {
     /// List all the available physical devices for the provided instance
+    ///
+    /// Returns [`Error::NoVulkanDevices`](super::Error::NoVulkanDevices) if there are none
     pub fn list(instance: I) -> super::Result<Self> {
-        let devices = unsafe { instance.vk_instance().enumerate_physical_devices()? };
+        let backend = instance.vk_backend();
+        let devices = unsafe { backend.enumerate_physical_devices() }
+            .context_of(Operation::EnumeratePhysicalDevices, backend.handle())?;
+        if devices.is_empty() {
+            return Err(super::Error::NoVulkanDevices { hint: icd_hint() });
+        }
         Ok(Self { instance, devices })
     }
 
     /// Returns an iterator over all the physical device handles
     pub fn iter(&self) -> impl Iterator<Item = PhysicalDevRef<'_, I>> {
-        self.devices.iter().map(|&device| PhysicalDevRef {
-            instance: &self.instance,
-            handle: device,
-        })
+        self.devices
+            .iter()
+            .map(|&device| PhysicalDevRef::new(&self.instance, device))
+    }
+
+    /// Returns the index of the device that matches the `selector`
+    ///
+    /// A device matches when it has the same vendor and device IDs,
+    /// if none does the first device whose name starts with the name
+    /// in the selector is returned (e.g. the same GPU on a different PCI slot or driver)
+    pub fn find_matching(&self, selector: &super::DeviceSelector) -> Option<usize> {
+        let devices: Vec<_> = self.iter().map(super::DeviceSelector::of).collect();
+        devices
+            .iter()
+            .position(|dev| {
+                dev.vendor_id == selector.vendor_id && dev.device_id == selector.device_id
+            })
+            .or_else(|| {
+                devices.iter().position(|dev| {
+                    !selector.device_name.is_empty()
+                        && dev.device_name.starts_with(&selector.device_name)
+                })
+            })
+    }
+
+    /// Returns the index of the best device according to `criteria` among the ones
+    /// for which `is_suitable` returns `true`
+    ///
+    /// Since the list is never empty, [`None`] means that no device qualifies.
+    ///
+    /// Devices with the same rank are picked in the order they have been enumerated.
+    ///
+    /// When [`DeviceCriteria::force_index`](super::DeviceCriteria::force_index) is set,
+    /// e.g. through [`DeviceCriteria::with_env_overrides`](super::DeviceCriteria::with_env_overrides),
+    /// that device is returned regardless of its type. If it's out of range or `is_suitable`
+    /// returns `false` for it [`Error::ForcedDeviceRejected`](super::Error::ForcedDeviceRejected)
+    /// is returned, rather than falling back to another device.
+    ///
+    /// The logical device for the returned index should be created with
+    /// [`select_checked`](Self::select_checked), so that invalid queue families are reported
+    /// as errors in release builds too, or use [`select_best_checked`](Self::select_best_checked)
+    /// which does both.
+    pub fn select_best(
+        &self,
+        criteria: &super::DeviceCriteria,
+        mut is_suitable: impl FnMut(PhysicalDevRef<'_, I>) -> bool,
+    ) -> super::Result<Option<usize>> {
+        if let Some(index) = criteria.force_index {
+            let reason = match self.iter().nth(index) {
+                None => format!("out of the {} devices", self.devices.len()),
+                Some(dev) if !is_suitable(dev) => "not suitable for the application".to_owned(),
+                Some(_) => return Ok(Some(index)),
+            };
+            return Err(super::Error::ForcedDeviceRejected { index, reason });
+        }
+        let devices: Vec<_> = self.iter().collect();
+        let types = devices.iter().map(|dev| dev.properties().device_type);
+        Ok(super::selection::best_device(criteria, types, |i| {
+            is_suitable(devices[i])
+        }))
+    }
+
+    /// Describes every device, along with the reasons for which it doesn't satisfy `criteria`
+    ///
+    /// When a surface is held along with the instance the devices are also required
+    /// to present to it and to support `VK_KHR_swapchain`.
+    ///
+    /// The suitable devices come first, sorted by rank; devices with the same rank
+    /// keep the order they have been enumerated in, like in [`Self::select_best`].
+    pub fn describe_all(&self, criteria: &super::DeviceCriteria) -> Vec<super::DeviceDescription> {
+        let has_surface = self.instance.vk_presentation_surface().is_some();
+        let reports = self.iter().map(|dev| {
+            let props = dev.properties();
+            let mem = dev.memory_properties();
+            super::selection::DeviceReport {
+                // SAFETY: The name is a null terminated string written by the Vulkan driver
+                name: unsafe { CStr::from_ptr(props.device_name.as_ptr()) }
+                    .to_string_lossy()
+                    .into_owned(),
+                device_type: props.device_type,
+                memory_heaps: mem.memory_heaps[..mem.memory_heap_count as usize].to_vec(),
+                can_present: dev
+                    .capabilities()
+                    .map(|caps| caps.can_present)
+                    .map_err(|err| err.to_string()),
+                supports_swapchain: has_surface.then(|| {
+                    dev.supports_extension(khr::Swapchain::name())
+                        .unwrap_or(false)
+                }),
+            }
+        });
+        super::selection::describe_devices(criteria, reports)
+    }
+
+    /// Returns the groups of physical devices available for the instance
+    ///
+    /// Device groups are queried through the core functions of Vulkan 1.1,
+    /// or through the `VK_KHR_device_group_creation` instance extension if enabled.
+    /// When neither is available every device is reported in a group on its own,
+    /// check [`supports_device_groups`](Self::supports_device_groups).
+    pub fn device_groups(&self) -> super::Result<Vec<DeviceGroup>> {
+        let info = self.instance.vk_info();
+        let (entry, vk_instance) = (self.instance.vk_entry(), self.instance.vk_instance());
+        let groups = unsafe {
+            if info.api_version >= vk::API_VERSION_1_1 {
+                vk_instance
+                    .enumerate_physical_device_groups_len()
+                    .and_then(|len| {
+                        let mut groups = vec![Default::default(); len];
+                        vk_instance.enumerate_physical_device_groups(&mut groups)?;
+                        Ok(groups)
+                    })
+            } else if info.has_extension(khr::DeviceGroupCreation::name()) {
+                let fns = khr::DeviceGroupCreation::new(entry.clone(), vk_instance);
+                fns.enumerate_physical_device_groups_len().and_then(|len| {
+                    let mut groups = vec![Default::default(); len];
+                    fns.enumerate_physical_device_groups(&mut groups)?;
+                    Ok(groups)
+                })
+            } else {
+                return Ok((0..self.devices.len())
+                    .map(|i| DeviceGroup {
+                        devices: vec![i],
+                        subset_allocation: false,
+                    })
+                    .collect());
+            }
+        }
+        .context_of(
+            Operation::EnumeratePhysicalDeviceGroups,
+            vk_instance.handle(),
+        )?;
+
+        Ok(groups
+            .iter()
+            .map(|group: &vk::PhysicalDeviceGroupProperties| DeviceGroup {
+                devices: group.physical_devices[..group.physical_device_count as usize]
+                    .iter()
+                    .filter_map(|dev| self.devices.iter().position(|d| d == dev))
+                    .collect(),
+                subset_allocation: group.subset_allocation == vk::TRUE,
+            })
+            .collect())
     }
 
     /// Selects the physical device at `index` and a list of queue family indices
@@ -78,9 +261,6 @@ impl<I: super::InstanceHolder> PhysicalDevList<I> {
     ///
     /// `queue_family_infos` must be valid for the selected physical device.
     ///
-    /// `extensions` must contain pointers to null terminated strings,
-    /// it should be considered as a slice of [`&CStr`](std::ffi::CStr)
-    ///
     /// Check the documentation of [`vku::QueueFamilyInfo`](super::QueueFamilyInfo)
     /// to know what valid means.
     ///
@@ -88,11 +268,177 @@ impl<I: super::InstanceHolder> PhysicalDevList<I> {
     ///
     /// `extensions` must not contain both `"VK_KHR_buffer_device_address"` and `"VK_EXT_buffer_device_address"`
     pub unsafe fn select(
+        self,
+        selected_dev: usize,
+        queue_family_infos: Vec<super::QueueFamilyInfo>,
+        extensions: impl Into<super::ExtensionList>,
+    ) -> super::Result<super::LogicalDev<I>> {
+        let extensions = extensions.into();
+        self.create_device(&[selected_dev], queue_family_infos, &extensions, None)
+    }
+
+    /// Same as [`select`](Self::select) but checks the validity of `queue_family_infos`
+    /// in all build profiles, returning
+    /// [`Error::InvalidQueueInfos`](super::Error::InvalidQueueInfos) instead of asserting,
+    /// and [`Error::DeviceIndexOutOfRange`](super::Error::DeviceIndexOutOfRange)
+    /// instead of panicking if `selected_dev` is outside the list of available devices
+    ///
+    /// # Safety
+    ///
+    /// Same as [`select`](Self::select), apart from the validity of `queue_family_infos`
+    pub unsafe fn select_checked(
+        self,
+        selected_dev: usize,
+        queue_family_infos: Vec<super::QueueFamilyInfo>,
+        extensions: impl Into<super::ExtensionList>,
+    ) -> super::Result<super::LogicalDev<I>> {
+        let Some(&handle) = self.devices.get(selected_dev) else {
+            return Err(super::Error::DeviceIndexOutOfRange {
+                index: selected_dev,
+                count: self.devices.len(),
+            });
+        };
+        let families = PhysicalDevRef::new(&self.instance, handle).queue_families();
+        super::queue_family::validate(&queue_family_infos, &families)
+            .map_err(|reason| super::Error::InvalidQueueInfos { reason })?;
+        self.select(selected_dev, queue_family_infos, extensions)
+    }
+
+    /// Selects the best device like [`select_best`](Self::select_best) and creates
+    /// the logical device through [`select_checked`](Self::select_checked)
+    ///
+    /// `queue_families` returns the queue families to create for a device,
+    /// or [`None`] if the device is not suitable. It's called one last time for the selected
+    /// device, right before creating the logical device, so the values computed for it
+    /// can be captured by the closure.
+    ///
+    /// Returns [`None`] if no device qualifies.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`select_checked`](Self::select_checked)
+    pub unsafe fn select_best_checked(
+        self,
+        criteria: &super::DeviceCriteria,
+        mut queue_families: impl FnMut(PhysicalDevRef<'_, I>) -> Option<Vec<super::QueueFamilyInfo>>,
+        extensions: impl Into<super::ExtensionList>,
+    ) -> super::Result<Option<super::LogicalDev<I>>> {
+        let Some(index) = self.select_best(criteria, |dev| queue_families(dev).is_some())? else {
+            return Ok(None);
+        };
+        let dev = PhysicalDevRef::new(&self.instance, self.devices[index]);
+        let Some(infos) = queue_families(dev) else {
+            return Ok(None);
+        };
+        self.select_checked(index, infos, extensions).map(Some)
+    }
+
+    /// Same as [`select`](Self::select) but takes the extension names as raw pointers
+    ///
+    /// # Panics
+    ///
+    /// Same as [`select`](Self::select)
+    ///
+    /// # Safety
+    ///
+    /// Same as [`select`](Self::select), additionally `extensions` must contain pointers
+    /// to null terminated strings
+    pub unsafe fn select_raw(
         self,
         selected_dev: usize,
         queue_family_infos: Vec<super::QueueFamilyInfo>,
         extensions: &[*const c_char],
     ) -> super::Result<super::LogicalDev<I>> {
+        let extensions = super::ExtensionList::from_raw(extensions);
+        self.create_device(&[selected_dev], queue_family_infos, &extensions, None)
+    }
+
+    /// Same as [`select`](Self::select) but adds `next` to the `p_next` chain of the
+    /// [`vk::DeviceCreateInfo`], for the parameters that aren't modeled
+    /// (e.g. the features structures)
+    ///
+    /// # Panics
+    ///
+    /// Same as [`select`](Self::select)
+    ///
+    /// # Safety
+    ///
+    /// Same as [`select`](Self::select), additionally `next` must be a valid structure
+    /// along with its own `p_next` chain
+    pub unsafe fn select_chained<T: vk::ExtendsDeviceCreateInfo>(
+        self,
+        selected_dev: usize,
+        queue_family_infos: Vec<super::QueueFamilyInfo>,
+        extensions: impl Into<super::ExtensionList>,
+        next: &mut T,
+    ) -> super::Result<super::LogicalDev<I>> {
+        let next = next as *mut T as *mut vk::BaseOutStructure;
+        let extensions = extensions.into();
+        self.create_device(&[selected_dev], queue_family_infos, &extensions, Some(next))
+    }
+
+    /// Returns whether or not logical devices can be created over groups of more than one
+    /// physical device, which requires the instance to be created either for Vulkan 1.1,
+    /// check [`InstanceBuilder::api_version`](super::InstanceBuilder::api_version),
+    /// or with the `VK_KHR_device_group_creation` extension enabled
+    pub fn supports_device_groups(&self) -> bool {
+        let info = self.instance.vk_info();
+        info.api_version >= vk::API_VERSION_1_1
+            || info.has_extension(khr::DeviceGroupCreation::name())
+    }
+
+    /// Creates a single logical device over all the physical devices in `group`
+    ///
+    /// The first device of the group is the one whose properties, queue families
+    /// and extensions are used for the logical device.
+    ///
+    /// Returns [`Error::DeviceGroupsUnsupported`](super::Error::DeviceGroupsUnsupported)
+    /// if the group has more than one device and the instance doesn't
+    /// [support device groups](Self::supports_device_groups).
+    ///
+    /// # Panics
+    ///
+    /// If `group` is empty or contains indices outside the list of available physical devices
+    ///
+    /// ## Debug Only
+    ///
+    /// Same as [`select`](Self::select)
+    ///
+    /// # Safety
+    ///
+    /// Same as [`select`](Self::select), where the selected device is the first of the group
+    pub unsafe fn select_group(
+        self,
+        group: &DeviceGroup,
+        queue_family_infos: Vec<super::QueueFamilyInfo>,
+        extensions: impl Into<super::ExtensionList>,
+    ) -> super::Result<super::LogicalDev<I>> {
+        if group.devices.len() > 1 && !self.supports_device_groups() {
+            return Err(super::Error::DeviceGroupsUnsupported);
+        }
+        let extensions = extensions.into();
+        self.create_device(&group.devices, queue_family_infos, &extensions, None)
+    }
+
+    /// Creates a logical device over the physical devices at the `selected` indices
+    ///
+    /// # Safety
+    ///
+    /// Same as [`select_group`](Self::select_group), `next` must point to a valid
+    /// structure that extends [`vk::DeviceCreateInfo`]
+    unsafe fn create_device(
+        self,
+        selected: &[usize],
+        queue_family_infos: Vec<super::QueueFamilyInfo>,
+        extensions: &super::ExtensionList,
+        next: Option<*mut vk::BaseOutStructure>,
+    ) -> super::Result<super::LogicalDev<I>> {
+        trace_span!(
+            "create_device",
+            devices = ?selected,
+            queue_families = queue_family_infos.len(),
+            extensions = extensions.len(),
+        );
         // Can't have a device with zero queues enabled
         debug_assert!(!queue_family_infos.is_empty());
         // Can't create two separate queues of the same family
@@ -104,54 +450,320 @@ impl<I: super::InstanceHolder> PhysicalDevList<I> {
         let queue_create_infos: Vec<_> =
             queue_family_infos.iter().map(|i| i.create_info()).collect();
 
+        let group: Vec<_> = selected.iter().map(|&i| self.devices[i]).collect();
+        let phydev = group[0];
+
+        let extension_ptrs = extensions.as_ptrs();
+        let mut group_info = vk::DeviceGroupDeviceCreateInfo::builder().physical_devices(&group);
         let create_info = vk::DeviceCreateInfo::builder()
             .queue_create_infos(&queue_create_infos)
-            .enabled_extension_names(extensions)
-            .build();
-
-        let phydev = *self.devices.get(selected_dev).unwrap();
+            .enabled_extension_names(&extension_ptrs);
+        // A group of a single device is the same as not using groups at all,
+        // and doesn't require Vulkan 1.1 or VK_KHR_device_group_creation
+        let mut create_info = match group.len() {
+            1 => create_info,
+            _ => create_info.push_next(&mut group_info),
+        }
+        .build();
+        if let Some(next) = next {
+            super::chain::prepend(&mut create_info.p_next, next);
+        }
         let device = self
             .instance
-            .vk_instance()
-            .create_device(phydev, &create_info, None)?;
+            .vk_backend()
+            .create_device(phydev, &create_info, self.instance.vk_allocator())
+            .context_of(Operation::CreateDevice, phydev)?;
+
+        let info = super::logical_dev::DeviceInfo {
+            physical_device: phydev,
+            queue_families: queue_family_infos,
+            extensions: extensions.names().to_vec(),
+        };
+        super::LogicalDev::new(self.instance, device, info, true)
+    }
+}
+
+/// Checks the driver manifests listed in the `VK_DRIVER_FILES` or `VK_ICD_FILENAMES`
+/// environment variables, returning a description of the ones that don't exist
+///
+/// Returns [`None`] if the variables are not set or all the manifests exist
+fn icd_hint() -> Option<String> {
+    let (var, value) = ["VK_DRIVER_FILES", "VK_ICD_FILENAMES"]
+        .into_iter()
+        .find_map(|var| Some((var, std::env::var_os(var)?)))?;
+    let missing: Vec<_> = std::env::split_paths(&value)
+        .filter(|path| !path.as_os_str().is_empty() && !path.exists())
+        .map(|path| path.display().to_string())
+        .collect();
+    if value.is_empty() {
+        Some(format!("{var} is set but empty"))
+    } else if missing.is_empty() {
+        None
+    } else {
+        Some(format!("{var} lists missing files: {}", missing.join(", ")))
+    }
+}
 
-        Ok(super::LogicalDev::new(self.instance, device))
+#[cfg(feature = "surface")]
+impl<I: super::SurfaceHolder> PhysicalDevList<I> {
+    /// Returns the index of the device that is most likely driving the surface,
+    /// whose window has a size of `window_extent`
+    ///
+    /// Check [`vku::selection::pick_presenting_device`](super::selection::pick_presenting_device)
+    /// for how the device is chosen.
+    ///
+    /// Returns [`None`] if no device can present to the surface
+    pub fn presenting_device(&self, window_extent: vk::Extent2D) -> super::Result<Option<usize>> {
+        let candidates = self
+            .iter()
+            .map(|dev| {
+                let families = dev.queue_families().len() as u32;
+                let mut supports_present = false;
+                for family in 0..families {
+                    // SAFETY: The index is in the range of the available queue families
+                    if unsafe { dev.supports_surface(family)? } {
+                        supports_present = true;
+                        break;
+                    }
+                }
+                let current_extent = match supports_present {
+                    // SAFETY: The device supports the surface
+                    true => unsafe { dev.surface_capabilities()? }.current_extent,
+                    false => vk::Extent2D::default(),
+                };
+                Ok(super::PresentCandidate {
+                    supports_present,
+                    current_extent,
+                })
+            })
+            .collect::<super::Result<Vec<_>>>()?;
+        Ok(super::selection::pick_presenting_device(
+            &candidates,
+            window_extent,
+        ))
     }
 }
 
-impl<I: super::InstanceHolder> PhysicalDevRef<'_, I> {
+impl<'a, I: super::InstanceHolder> PhysicalDevRef<'a, I> {
+    /// Creates a reference to a device handle enumerated from `instance`
+    pub(super) fn new(instance: &'a I, handle: vk::PhysicalDevice) -> Self {
+        Self { instance, handle }
+    }
+
     fn vk_instance(&self) -> &ash::Instance {
         self.instance.vk_instance()
     }
 
+    /// Returns a summary of what this device can do
+    ///
+    /// The presentation support is checked for the surface held along with the instance, if any
+    pub fn capabilities(&self) -> super::Result<super::DeviceCapabilities> {
+        let (props, features) = (self.properties(), self.features());
+        let families = self.queue_families();
+        let has = |flags: vk::QueueFlags, fam: &vk::QueueFamilyProperties| {
+            fam.queue_flags.contains(flags)
+        };
+
+        let can_present = match self.instance.vk_presentation_surface() {
+            Some((fns, surface)) => {
+                let mut supported = false;
+                for family in 0..families.len() as u32 {
+                    supported |= unsafe {
+                        fns.get_physical_device_surface_support(self.handle, family, surface)
+                    }
+                    .context_of(Operation::GetSurfaceSupport, self.handle)?;
+                }
+                Some(supported)
+            }
+            None => None,
+        };
+
+        let limits = props.limits;
+        let samples =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+        // The sample counts are powers of two, so the highest bit is the maximum
+        let max_msaa_samples = match samples.as_raw() {
+            0 => 1,
+            raw => 1 << (31 - raw.leading_zeros()),
+        };
+
+        let extensions = self.extension_properties()?;
+        let has_extension = |name: &CStr| {
+            extensions
+                .iter()
+                // SAFETY: This pointer was generated by the Vulkan driver
+                .any(|prop| unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) } == name)
+        };
+
+        Ok(super::DeviceCapabilities {
+            can_present,
+            compute_queue: families.iter().any(|fam| has(vk::QueueFlags::COMPUTE, fam)),
+            dedicated_transfer_family: families
+                .iter()
+                .position(|fam| {
+                    has(vk::QueueFlags::TRANSFER, fam)
+                        && !fam
+                            .queue_flags
+                            .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                })
+                .map(|i| i as u32),
+            max_texture_size: limits.max_image_dimension2_d,
+            max_msaa_samples,
+            bc_compression: features.texture_compression_bc == vk::TRUE,
+            etc2_compression: features.texture_compression_etc2 == vk::TRUE,
+            astc_compression: features.texture_compression_astc_ldr == vk::TRUE,
+            ray_tracing: has_extension(khr::RayTracingPipeline::name())
+                && has_extension(khr::AccelerationStructure::name()),
+            mesh_shader: has_extension(vk::ExtMeshShaderFn::name())
+                || has_extension(vk::NvMeshShaderFn::name()),
+            max_push_constants_size: limits.max_push_constants_size,
+        })
+    }
+
+    /// Returns how the features promoted to core are provided by this device,
+    /// considering all the extensions it supports
+    ///
+    /// The [`extensions`](super::DeviceCapabilitiesResolved::extensions) of the returned value
+    /// are the ones to enable, the features already in core don't need them.
+    pub fn resolve_promoted(&self) -> super::Result<super::DeviceCapabilitiesResolved> {
+        let extensions = self.extension_properties()?;
+        let api_version = self
+            .instance
+            .vk_info()
+            .api_version
+            .min(self.properties().api_version);
+        Ok(super::DeviceCapabilitiesResolved::resolve(
+            api_version,
+            |name| {
+                extensions
+                    .iter()
+                    // SAFETY: This pointer was generated by the Vulkan driver
+                    .any(|prop| unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) } == name)
+            },
+        ))
+    }
+
     /// Returns the properties of this physical device
     pub fn properties(&self) -> vk::PhysicalDeviceProperties {
-        let i = self.vk_instance();
-        unsafe { i.get_physical_device_properties(self.handle) }
+        let backend = self.instance.vk_backend();
+        unsafe { backend.get_physical_device_properties(self.handle) }
     }
 
     /// Returns the features of this physical device
     pub fn features(&self) -> vk::PhysicalDeviceFeatures {
-        let i = self.vk_instance();
-        unsafe { i.get_physical_device_features(self.handle) }
+        let backend = self.instance.vk_backend();
+        unsafe { backend.get_physical_device_features(self.handle) }
     }
 
     /// Returns the list of queue families supported
     pub fn queue_families(&self) -> Vec<vk::QueueFamilyProperties> {
-        let i = self.vk_instance();
-        unsafe { i.get_physical_device_queue_family_properties(self.handle) }
+        let backend = self.instance.vk_backend();
+        unsafe { backend.get_physical_device_queue_family_properties(self.handle) }
     }
 
     /// Returns the list of queue families supported
     pub fn extension_properties(&self) -> super::Result<Vec<vk::ExtensionProperties>> {
-        let i = self.vk_instance();
-        unsafe { i.enumerate_device_extension_properties(self.handle) }
+        let backend = self.instance.vk_backend();
+        unsafe { backend.enumerate_device_extension_properties(self.handle) }
+            .context_of(Operation::EnumerateDeviceExtensions, self.handle)
+    }
+
+    /// Returns whether or not the device extension `name` is supported
+    pub fn supports_extension(&self, name: &CStr) -> super::Result<bool> {
+        Ok(self
+            .extension_properties()?
+            .iter()
+            // SAFETY: This pointer was generated by the Vulkan driver
+            .any(|prop| unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) } == name))
+    }
+
+    /// Returns the memory heaps and types of this physical device
+    pub fn memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        let backend = self.instance.vk_backend();
+        unsafe { backend.get_physical_device_memory_properties(self.handle) }
+    }
+
+    /// Returns the functions to use for the `vkGetPhysicalDevice*2` queries,
+    /// or [`None`] if neither the instance nor the device support them
+    ///
+    /// The core functions are used when the instance has been created for Vulkan 1.1 or later
+    /// and the device supports it, the extension is used otherwise
+    fn properties2_fns(&self) -> Option<Properties2Fns> {
+        let info = self.instance.vk_info();
+        if info.api_version >= vk::API_VERSION_1_1
+            && self.properties().api_version >= vk::API_VERSION_1_1
+        {
+            Some(Properties2Fns::Core)
+        } else if info.has_extension(khr::GetPhysicalDeviceProperties2::name()) {
+            let (entry, instance) = (self.instance.vk_entry(), self.vk_instance());
+            let fns = khr::GetPhysicalDeviceProperties2::new(entry, instance);
+            Some(Properties2Fns::Khr(fns))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the budget and the current usage of each memory heap of this device
+    ///
+    /// The values are queried through the `VK_EXT_memory_budget` device extension,
+    /// which also requires either a Vulkan 1.1 instance and device, check
+    /// [`InstanceBuilder::api_version`](super::InstanceBuilder::api_version), or the
+    /// `VK_KHR_get_physical_device_properties2` instance extension to be enabled.
+    /// When that's not possible the budget of each heap is reported as its size
+    /// and the usage is unknown.
+    pub fn memory_budget(&self) -> super::Result<Vec<HeapBudget>> {
+        let fns = match self.properties2_fns() {
+            Some(fns) if self.supports_extension(vk::ExtMemoryBudgetFn::name())? => fns,
+            _ => {
+                let props = self.memory_properties();
+                let heaps = &props.memory_heaps[..props.memory_heap_count as usize];
+                return Ok(heaps
+                    .iter()
+                    .map(|heap| HeapBudget {
+                        flags: heap.flags,
+                        size: heap.size,
+                        budget: heap.size,
+                        usage: None,
+                    })
+                    .collect());
+            }
+        };
+
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut props = vk::PhysicalDeviceMemoryProperties2::builder().push_next(&mut budget);
+        unsafe {
+            match fns {
+                Properties2Fns::Core => self
+                    .vk_instance()
+                    .get_physical_device_memory_properties2(self.handle, &mut props),
+                Properties2Fns::Khr(fns) => {
+                    fns.get_physical_device_memory_properties2(self.handle, &mut props)
+                }
+            }
+        }
+        let props = props.memory_properties;
+
+        let heaps = &props.memory_heaps[..props.memory_heap_count as usize];
+        Ok(heaps
+            .iter()
+            .enumerate()
+            .map(|(i, heap)| HeapBudget {
+                flags: heap.flags,
+                size: heap.size,
+                budget: budget.heap_budget[i],
+                usage: Some(budget.heap_usage[i]),
+            })
+            .collect())
     }
 }
 
+#[cfg(feature = "surface")]
 impl<I: super::SurfaceHolder> PhysicalDevRef<'_, I> {
-    fn vk_surface(&self) -> (&khr::Surface, &vk::SurfaceKHR) {
-        (self.instance.vk_surface_fns(), self.instance.vk_surface())
+    fn vk_surface(&self) -> (&dyn super::backend::SurfaceBackend, &vk::SurfaceKHR) {
+        (
+            self.instance.vk_surface_backend(),
+            self.instance.vk_surface(),
+        )
     }
 
     /// Returns whether or not the [`vku::Surface`](super::Surface) bound to the
@@ -164,38 +776,374 @@ impl<I: super::SurfaceHolder> PhysicalDevRef<'_, I> {
     pub unsafe fn supports_surface(&self, queue_family_index: u32) -> super::Result<bool> {
         let (fns, surface) = self.vk_surface();
         fns.get_physical_device_surface_support(self.handle, queue_family_index, *surface)
+            .context_of(Operation::GetSurfaceSupport, self.handle)
     }
 
     /// Returns the capabilities that this devices has for the surface
     ///
+    /// The value is cached by the surface until [`vku::surface::refresh`](super::surface::refresh)
+    /// is called
+    ///
     /// # Safety
     ///
     /// The device must support the surface,
     /// check the [`supports_surface`](Self::supports_surface) method
     pub unsafe fn surface_capabilities(&self) -> super::Result<vk::SurfaceCapabilitiesKHR> {
-        let (fns, surface) = self.vk_surface();
-        fns.get_physical_device_surface_capabilities(self.handle, *surface)
+        Ok(self.surface_info()?.capabilities)
     }
 
     /// Returns the supported color formats by this devices for the surface
     ///
+    /// The value is cached by the surface until [`vku::surface::refresh`](super::surface::refresh)
+    /// is called
+    ///
     /// # Safety
     ///
     /// The device must support the surface,
     /// check the [`supports_surface`](Self::supports_surface) method
     pub unsafe fn surface_formats(&self) -> super::Result<Vec<vk::SurfaceFormatKHR>> {
-        let (fns, surface) = self.vk_surface();
-        fns.get_physical_device_surface_formats(self.handle, *surface)
+        Ok(self.surface_info()?.formats)
     }
 
     /// Returns the supported present modes by this devices for the surface
     ///
+    /// The value is cached by the surface until [`vku::surface::refresh`](super::surface::refresh)
+    /// is called
+    ///
     /// # Safety
     ///
     /// The device must support the surface,
     /// check the [`supports_surface`](Self::supports_surface) method
     pub unsafe fn surface_present_modes(&self) -> super::Result<Vec<vk::PresentModeKHR>> {
-        let (fns, surface) = self.vk_surface();
-        fns.get_physical_device_surface_present_modes(self.handle, *surface)
+        Ok(self.surface_info()?.present_modes)
+    }
+
+    /// Returns the capabilities, the formats and the present modes of this device
+    /// for the surface at once, check [`vku::surface::info`](super::surface::info)
+    ///
+    /// # Safety
+    ///
+    /// The device must support the surface,
+    /// check the [`supports_surface`](Self::supports_surface) method
+    pub unsafe fn surface_info(&self) -> super::Result<super::surface::SurfaceInfo> {
+        super::surface::info(self.instance, self.handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock;
+
+    use vk::PhysicalDeviceType as Ty;
+
+    const NO_NAMES: [&CStr; 0] = [];
+
+    /// The operations supported by the queue family of the [`mock::Device::new`] devices
+    const ALL_QUEUES: vk::QueueFlags = vk::QueueFlags::from_raw(
+        vk::QueueFlags::GRAPHICS.as_raw()
+            | vk::QueueFlags::COMPUTE.as_raw()
+            | vk::QueueFlags::TRANSFER.as_raw(),
+    );
+
+    fn queues(index: u32) -> Vec<super::super::QueueFamilyInfo> {
+        vec![super::super::QueueFamilyInfo {
+            index,
+            priorities: vec![1.0],
+        }]
+    }
+
+    fn presenting(device: mock::Device) -> mock::Device {
+        let format = vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        device.extension(khr::Swapchain::name()).presenting(
+            Default::default(),
+            vec![format],
+            vec![vk::PresentModeKHR::FIFO],
+        )
+    }
+
+    #[test]
+    fn list_without_devices() {
+        let result = PhysicalDevList::list(mock::Instance::new(vec![]));
+        assert!(matches!(
+            result,
+            Err(super::super::Error::NoVulkanDevices { .. })
+        ));
+    }
+
+    #[test]
+    fn select_best_prefers_the_rank() {
+        let list = PhysicalDevList::list(mock::Instance::new(vec![
+            mock::Device::new("integrated", Ty::INTEGRATED_GPU, 1 << 30),
+            mock::Device::new("cpu", Ty::CPU, 0),
+            mock::Device::new("discrete", Ty::DISCRETE_GPU, 8 << 30),
+            mock::Device::new("discrete 2", Ty::DISCRETE_GPU, 4 << 30),
+        ]))
+        .unwrap();
+        let criteria = super::super::DeviceCriteria::default();
+        assert_eq!(list.select_best(&criteria, |_| true), Ok(Some(2)));
+        let without_discrete =
+            |dev: PhysicalDevRef<'_, _>| dev.properties().device_type != Ty::DISCRETE_GPU;
+        assert_eq!(list.select_best(&criteria, without_discrete), Ok(Some(0)));
+        let software = criteria.allow_software(true);
+        assert_eq!(list.select_best(&software, |_| false), Ok(None));
+    }
+
+    #[test]
+    fn select_best_checked_creates_the_queues() {
+        let mut device = mock::Device::new("gpu", Ty::DISCRETE_GPU, 1 << 30);
+        device.queue_families.push(vk::QueueFamilyProperties {
+            queue_flags: vk::QueueFlags::TRANSFER,
+            queue_count: 2,
+            ..Default::default()
+        });
+        device.present_families = vec![true];
+        let instance = mock::Instance::new(vec![device]).with_surface();
+        let list = PhysicalDevList::list(instance).unwrap();
+
+        let criteria = super::super::DeviceCriteria::default();
+        let families = || {
+            let mut families = queues(0);
+            families.push(super::super::QueueFamilyInfo {
+                index: 1,
+                priorities: vec![1.0, 0.5],
+            });
+            Some(families)
+        };
+        let dev = unsafe { list.select_best_checked(&criteria, |_| families(), NO_NAMES) }
+            .unwrap()
+            .unwrap();
+        let summary: Vec<_> = dev
+            .queues()
+            .iter()
+            .map(|q| (q.family_index, q.index, q.flags, q.supports_present))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, 0, ALL_QUEUES, true),
+                (1, 0, vk::QueueFlags::TRANSFER, false),
+                (1, 1, vk::QueueFlags::TRANSFER, false),
+            ]
+        );
+        assert_eq!(dev.info().physical_device, mock::Instance::device_handle(0));
+        assert_eq!(dev.present_queue(), dev.queues().first());
+    }
+
+    #[test]
+    fn invalid_queue_families_create_no_device() {
+        let instance = mock::Instance::new(vec![mock::Device::new("gpu", Ty::DISCRETE_GPU, 0)]);
+        let live_devices = instance.live_devices();
+        let list = PhysicalDevList::list(instance).unwrap();
+        let result = unsafe { list.select_checked(0, queues(1), NO_NAMES) };
+        assert!(matches!(
+            result,
+            Err(super::super::Error::InvalidQueueInfos { .. })
+        ));
+        assert_eq!(live_devices.get(), 0);
+    }
+
+    #[test]
+    fn failed_queue_retrieval_destroys_the_device() {
+        let mut device = mock::Device::new("gpu", Ty::DISCRETE_GPU, 0);
+        device.present_error = Some(vk::Result::ERROR_SURFACE_LOST_KHR);
+        let instance = mock::Instance::new(vec![device]).with_surface();
+        let live_devices = instance.live_devices();
+        let list = PhysicalDevList::list(instance).unwrap();
+        let Err(err) = (unsafe { list.select_checked(0, queues(0), NO_NAMES) }) else {
+            panic!("the device has been created");
+        };
+        assert_eq!(err.vk_result(), Some(vk::Result::ERROR_SURFACE_LOST_KHR));
+        let operations: Vec<_> = err.contexts().map(|ctx| ctx.operation).collect();
+        assert_eq!(operations, [Operation::GetSurfaceSupport]);
+        assert_eq!(live_devices.get(), 0);
+    }
+
+    #[test]
+    fn logical_device_is_destroyed_on_drop() {
+        let instance = mock::Instance::new(vec![mock::Device::new("gpu", Ty::DISCRETE_GPU, 0)]);
+        let live_devices = instance.live_devices();
+        let list = PhysicalDevList::list(instance).unwrap();
+        let dev = unsafe { list.select_checked(0, queues(0), NO_NAMES) }.unwrap();
+        assert_eq!(live_devices.get(), 1);
+        drop(dev);
+        assert_eq!(live_devices.get(), 0);
+    }
+
+    #[test]
+    fn describe_all_checks_the_surface() {
+        let mut failing = presenting(mock::Device::new("failing", Ty::DISCRETE_GPU, 0));
+        failing.extensions_error = Some(vk::Result::ERROR_OUT_OF_HOST_MEMORY);
+        let instance = mock::Instance::new(vec![
+            mock::Device::new("headless", Ty::DISCRETE_GPU, 0),
+            presenting(mock::Device::new("presenting", Ty::INTEGRATED_GPU, 0)),
+            failing,
+        ])
+        .with_surface();
+        let list = PhysicalDevList::list(instance).unwrap();
+        let descriptions = list.describe_all(&super::super::DeviceCriteria::default());
+
+        let names: Vec<_> = descriptions.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["presenting", "headless", "failing"]);
+        assert!(descriptions[0].is_suitable());
+        assert_eq!(
+            descriptions[1].reasons,
+            [
+                "no queue family can present to the window",
+                "missing extension VK_KHR_swapchain",
+            ]
+        );
+        let failure = &descriptions[2].reasons;
+        assert!(
+            failure[0].starts_with("failed to query the device: enumerating device extensions"),
+            "{failure:?}"
+        );
+    }
+
+    #[test]
+    fn describe_all_without_surface() {
+        let list = PhysicalDevList::list(mock::Instance::new(vec![mock::Device::new(
+            "headless",
+            Ty::DISCRETE_GPU,
+            0,
+        )]))
+        .unwrap();
+        let descriptions = list.describe_all(&super::super::DeviceCriteria::default());
+        assert!(descriptions[0].is_suitable(), "{descriptions:?}");
+    }
+
+    /// The size of the window the fixture profiles have been captured with
+    #[cfg(feature = "surface")]
+    const FIXTURE_WINDOW: vk::Extent2D = vk::Extent2D {
+        width: 1280,
+        height: 720,
+    };
+
+    /// Describes the devices of a fixture profile and what gets selected
+    /// with and without software devices, picking the queues and the swapchain
+    /// details like `src/main.rs` does
+    #[cfg(feature = "surface")]
+    fn render_profile(profile: &mock::profile::Profile) -> String {
+        use std::fmt::Write;
+
+        let criteria = super::super::DeviceCriteria::default();
+        let list = PhysicalDevList::list(profile.instance()).unwrap();
+        let mut out = format!("{}\n", profile.description);
+        for desc in list.describe_all(&criteria) {
+            let super::super::DeviceDescription {
+                index,
+                name,
+                device_type,
+                rank,
+                reasons,
+                ..
+            } = desc;
+            writeln!(
+                out,
+                "device {index}: {name} ({device_type:?}), rank {rank:?}"
+            )
+            .unwrap();
+            for reason in reasons {
+                writeln!(out, "  {reason}").unwrap();
+            }
+        }
+
+        let prefs = super::super::SwapchainPreferences::default();
+        for (label, criteria) in [
+            ("default criteria", criteria.clone()),
+            ("software allowed", criteria.allow_software(true)),
+        ] {
+            let list = PhysicalDevList::list(profile.instance()).unwrap();
+            let mut details = None;
+            let queue_families = |dev: PhysicalDevRef<'_, _>| {
+                if !dev.supports_extension(khr::Swapchain::name()).ok()? {
+                    return None;
+                }
+                let families = dev.queue_families();
+                let (graphics, present) =
+                    super::super::queue_family::graphics_and_present(&families, |fam| {
+                        // SAFETY: The index is in the range of the queue families
+                        unsafe { dev.supports_surface(fam).unwrap_or(false) }
+                    })?;
+                // SAFETY: The present queue family supports the surface
+                let info = unsafe { dev.surface_info() }.ok()?;
+                details = Some(super::super::swapchain::ImageDetails::negotiate(
+                    &prefs,
+                    &info,
+                    FIXTURE_WINDOW,
+                    &[graphics, present],
+                )?);
+                let mut infos = queues(graphics);
+                if present != graphics {
+                    infos.extend(queues(present));
+                }
+                Some(infos)
+            };
+            let extensions = [khr::Swapchain::name()];
+            let selected =
+                unsafe { list.select_best_checked(&criteria, queue_families, extensions) };
+            let (Some(dev), Some(details)) = (selected.unwrap(), details) else {
+                writeln!(out, "{label}: no device").unwrap();
+                continue;
+            };
+            let index = (0..)
+                .find(|&i| mock::Instance::device_handle(i) == dev.info().physical_device)
+                .unwrap();
+            writeln!(out, "{label}: device {index}").unwrap();
+            for queue in dev.queues() {
+                let present = if queue.supports_present {
+                    ", present"
+                } else {
+                    ""
+                };
+                let (family, index, flags) = (queue.family_index, queue.index, queue.flags);
+                writeln!(out, "  queue {family}.{index}: {flags:?}{present}").unwrap();
+            }
+            let super::super::swapchain::ImageDetails {
+                count,
+                format,
+                color_space,
+                extent,
+                sharing,
+                transform,
+                present_mode,
+            } = details;
+            writeln!(
+                out,
+                "  swapchain: {count} images of {}x{}, {format:?} {color_space:?}, \
+                 {present_mode:?}, {transform:?}, {sharing:?}",
+                extent.width, extent.height
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// Checks what is selected for each profile in `tests/fixtures`
+    /// against the `.snap` file next to it
+    ///
+    /// Run with `VKU_UPDATE_SNAPSHOTS=1` to write the snapshots again
+    #[test]
+    #[cfg(feature = "surface")]
+    fn fixture_profiles() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        let update = std::env::var_os("VKU_UPDATE_SNAPSHOTS").is_some();
+        let mut mismatches = vec![];
+        for name in mock::profile::PROFILES {
+            let actual = render_profile(&mock::profile::Profile::load(name));
+            let path = format!("{dir}/{name}.snap");
+            if update {
+                std::fs::write(&path, &actual).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&path).unwrap_or_default();
+            if actual != expected {
+                mismatches.push(format!("{path}:\n{actual}"));
+            }
+        }
+        assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
     }
 }
@@ -0,0 +1,40 @@
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use ash::vk;
+
+/// A Vulkan queue handle along with the information about the family it belongs to
+///
+/// The queues are created along with the logical device,
+/// check [`vku::LogicalDev::queues`], and are then passed to
+/// [`vku::LogicalDev::create_command_pool`], [`vku::LogicalDev::submit`]
+/// and `Swapchain::present`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Queue {
+    /// The Vulkan queue handle
+    pub handle: vk::Queue,
+    /// Index of the queue family the queue belongs to
+    pub family_index: u32,
+    /// Index of the queue within its family
+    pub index: u32,
+    /// The operations supported by the queue family
+    pub flags: vk::QueueFlags,
+    /// Whether or not the queue can present to the surface the device has been created with,
+    /// always `false` if there is no surface
+    pub supports_present: bool,
+}
+
+impl super::RawHandle for Queue {
+    type Handle = vk::Queue;
+
+    fn raw(&self) -> vk::Queue {
+        self.handle
+    }
+}
+
+impl Queue {
+    /// Returns whether or not the queue supports all the operations in `flags`
+    pub fn supports(&self, flags: vk::QueueFlags) -> bool {
+        self.flags.contains(flags)
+    }
+}
@@ -9,9 +9,12 @@ use ash::vk;
 ///
 /// The validity depends on the physical device it refers to and consists of this checks:
 /// - `index` must be lower than the length of [`vku::PhysicalDevRef::queue_families`]
-/// - the length of `priorities` must be lower than the `queue_count` for the queue at `index`
-/// - the values in `priorities` must sum up to `1.0`
-#[derive(Clone)]
+/// - `priorities` must not be empty and its length must not exceed the `queue_count`
+///   of the family at `index`
+/// - the values in `priorities` must be between `0.0` and `1.0`
+///
+/// Those are checked by [`validate`](Self::validate)
+#[derive(Clone, Debug)]
 pub struct QueueFamilyInfo {
     pub index: u32,
     pub priorities: Vec<f32>,
@@ -30,4 +33,246 @@ impl QueueFamilyInfo {
             .queue_priorities(&self.priorities)
             .build()
     }
+
+    /// Checks the validity of this info for a device with the provided queue `families`
+    pub fn validate(
+        &self,
+        families: &[vk::QueueFamilyProperties],
+    ) -> Result<(), InvalidQueueInfos> {
+        let family = families
+            .get(self.index as usize)
+            .ok_or(InvalidQueueInfos::FamilyIndex {
+                index: self.index,
+                count: families.len() as u32,
+            })?;
+        let count = self.priorities.len() as u32;
+        if count == 0 || count > family.queue_count {
+            return Err(InvalidQueueInfos::QueueCount {
+                index: self.index,
+                count,
+                max: family.queue_count,
+            });
+        }
+        if !self.priorities.iter().all(|p| (0.0..=1.0).contains(p)) {
+            return Err(InvalidQueueInfos::Priority(self.index));
+        }
+        Ok(())
+    }
+}
+
+/// The reason why a list of [`QueueFamilyInfo`] is not valid for a device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidQueueInfos {
+    /// There are no queue families, a device must be created with at least one queue
+    Empty,
+    /// The same queue family is listed more than once
+    DuplicateFamily(u32),
+    /// The queue family index is outside the families of the device
+    FamilyIndex { index: u32, count: u32 },
+    /// The number of queues is zero or more than the family provides
+    QueueCount { index: u32, count: u32, max: u32 },
+    /// A priority of the queue family is outside the `0.0..=1.0` range
+    Priority(u32),
+}
+
+impl std::fmt::Display for InvalidQueueInfos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("no queue families"),
+            Self::DuplicateFamily(index) => write!(f, "queue family {index} listed more than once"),
+            Self::FamilyIndex { index, count } => {
+                write!(f, "queue family {index} out of the {count} available")
+            }
+            Self::QueueCount { index, count, max } => {
+                write!(
+                    f,
+                    "{count} queues for family {index}, must be between 1 and {max}"
+                )
+            }
+            Self::Priority(index) => write!(f, "priority out of range for family {index}"),
+        }
+    }
+}
+
+/// Checks the validity of the whole list of `infos` used to create a device
+/// with the provided queue `families`
+///
+/// Apart from checking each info with [`QueueFamilyInfo::validate`],
+/// the list must not be empty and each family must appear only once
+pub fn validate(
+    infos: &[QueueFamilyInfo],
+    families: &[vk::QueueFamilyProperties],
+) -> Result<(), InvalidQueueInfos> {
+    if infos.is_empty() {
+        return Err(InvalidQueueInfos::Empty);
+    }
+    for (i, info) in infos.iter().enumerate() {
+        if infos[..i].iter().any(|prev| prev.index == info.index) {
+            return Err(InvalidQueueInfos::DuplicateFamily(info.index));
+        }
+        info.validate(families)?;
+    }
+    Ok(())
+}
+
+/// Returns the indices of the graphics and present queue families, in this order
+///
+/// A family that supports both is preferred, since it allows the swapchain images
+/// to use [`vku::swapchain::ImageSharing::Exclusive`]; otherwise the first graphics family
+/// and the first family for which `supports_present` returns `true` are returned.
+///
+/// Returns [`None`] if either kind of family is missing
+pub fn graphics_and_present(
+    families: &[vk::QueueFamilyProperties],
+    mut supports_present: impl FnMut(u32) -> bool,
+) -> Option<(u32, u32)> {
+    let present: Vec<_> = (0..families.len() as u32)
+        .map(&mut supports_present)
+        .collect();
+    let is_graphics =
+        |fam: &vk::QueueFamilyProperties| fam.queue_flags.contains(vk::QueueFlags::GRAPHICS);
+
+    if let Some(both) = families
+        .iter()
+        .zip(&present)
+        .position(|(fam, &present)| is_graphics(fam) && present)
+    {
+        return Some((both as u32, both as u32));
+    }
+    let graphics = families.iter().position(is_graphics)?;
+    let present = present.iter().position(|&p| p)?;
+    Some((graphics as u32, present as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vk::QueueFlags as Q;
+
+    fn family(queue_flags: Q, queue_count: u32) -> vk::QueueFamilyProperties {
+        vk::QueueFamilyProperties {
+            queue_flags,
+            queue_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn graphics_and_present_same_family() {
+        let families = [
+            family(Q::COMPUTE | Q::TRANSFER, 2),
+            family(Q::GRAPHICS | Q::COMPUTE, 16),
+            family(Q::TRANSFER, 1),
+        ];
+        assert_eq!(graphics_and_present(&families, |_| true), Some((1, 1)));
+    }
+
+    #[test]
+    fn graphics_and_present_prefer_same_family() {
+        // The first graphics family can't present, but a later one can
+        let families = [family(Q::GRAPHICS, 1), family(Q::GRAPHICS, 1)];
+        assert_eq!(graphics_and_present(&families, |i| i != 0), Some((1, 1)));
+    }
+
+    #[test]
+    fn graphics_and_present_split_families() {
+        let families = [
+            family(Q::GRAPHICS | Q::COMPUTE, 16),
+            family(Q::TRANSFER, 1),
+            family(Q::COMPUTE, 4),
+        ];
+        assert_eq!(graphics_and_present(&families, |i| i == 2), Some((0, 2)));
+    }
+
+    #[test]
+    fn graphics_and_present_no_present_family() {
+        let families = [family(Q::GRAPHICS, 16), family(Q::COMPUTE, 4)];
+        assert_eq!(graphics_and_present(&families, |_| false), None);
+    }
+
+    #[test]
+    fn graphics_and_present_no_graphics_family() {
+        let families = [family(Q::COMPUTE, 4), family(Q::TRANSFER, 1)];
+        assert_eq!(graphics_and_present(&families, |_| true), None);
+        assert_eq!(graphics_and_present(&[], |_| true), None);
+    }
+
+    fn info(index: u32, queues: usize) -> QueueFamilyInfo {
+        QueueFamilyInfo {
+            index,
+            priorities: vec![1.0; queues],
+        }
+    }
+
+    #[test]
+    fn validate_accepts_valid_infos() {
+        let families = [family(Q::GRAPHICS, 2), family(Q::TRANSFER, 1)];
+        assert_eq!(validate(&[info(0, 2), info(1, 1)], &families), Ok(()));
+    }
+
+    #[test]
+    fn validate_empty_list() {
+        let families = [family(Q::GRAPHICS, 1)];
+        assert_eq!(validate(&[], &families), Err(InvalidQueueInfos::Empty));
+    }
+
+    #[test]
+    fn validate_out_of_range_index() {
+        let families = [family(Q::GRAPHICS, 1), family(Q::TRANSFER, 1)];
+        assert_eq!(
+            validate(&[info(0, 1), info(2, 1)], &families),
+            Err(InvalidQueueInfos::FamilyIndex { index: 2, count: 2 })
+        );
+    }
+
+    #[test]
+    fn validate_duplicate_family() {
+        let families = [family(Q::GRAPHICS, 4), family(Q::TRANSFER, 1)];
+        assert_eq!(
+            validate(&[info(0, 1), info(1, 1), info(0, 1)], &families),
+            Err(InvalidQueueInfos::DuplicateFamily(0))
+        );
+    }
+
+    #[test]
+    fn validate_queue_count_overflow() {
+        let families = [family(Q::GRAPHICS, 2)];
+        assert_eq!(
+            validate(&[info(0, 3)], &families),
+            Err(InvalidQueueInfos::QueueCount {
+                index: 0,
+                count: 3,
+                max: 2
+            })
+        );
+    }
+
+    #[test]
+    fn validate_empty_priorities() {
+        let families = [family(Q::GRAPHICS, 2)];
+        assert_eq!(
+            validate(&[info(0, 0)], &families),
+            Err(InvalidQueueInfos::QueueCount {
+                index: 0,
+                count: 0,
+                max: 2
+            })
+        );
+    }
+
+    #[test]
+    fn validate_priority_out_of_range() {
+        let families = [family(Q::GRAPHICS, 2)];
+        for priority in [-0.5, 1.5, f32::NAN] {
+            let info = QueueFamilyInfo {
+                index: 0,
+                priorities: vec![1.0, priority],
+            };
+            assert_eq!(
+                validate(&[info], &families),
+                Err(InvalidQueueInfos::Priority(0))
+            );
+        }
+    }
 }
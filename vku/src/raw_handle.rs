@@ -0,0 +1,44 @@
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use ash::vk;
+
+/// Access to the raw Vulkan handle wrapped by a [vku](crate) type,
+/// for the interoperation with other Vulkan crates or direct `ash` calls
+///
+/// The handle is still owned by the wrapper, so it must not be destroyed
+/// and must not be used after the wrapper has been dropped.
+///
+/// # Examples
+///
+/// Hand the device and a queue to code that builds its own `ash` function table,
+/// like most of the other Vulkan crates do
+///
+/// ```no_run
+/// # fn example(logical_dev: &vku::LogicalDev<vku::Instance>) -> ash::prelude::VkResult<()> {
+/// use ash::vk;
+/// use vku::RawHandle;
+///
+/// let instance = logical_dev.instance();
+/// // SAFETY: the device is still alive and is not destroyed through the loaded table
+/// let device = unsafe { ash::Device::load(instance.vk_instance().fp_v1_0(), logical_dev.raw()) };
+/// let queue = &logical_dev.queues()[0];
+///
+/// let pool_info = vk::CommandPoolCreateInfo::builder().queue_family_index(queue.family_index);
+/// // The objects created this way must use the same allocator of the instance
+/// let pool = unsafe { device.create_command_pool(&pool_info, instance.vk_allocator())? };
+/// // ... record and submit the work ...
+/// unsafe {
+///     device.queue_wait_idle(queue.raw())?;
+///     device.destroy_command_pool(pool, instance.vk_allocator());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait RawHandle {
+    /// The type of the Vulkan handle
+    type Handle: vk::Handle + Copy;
+
+    /// Returns the raw Vulkan handle
+    fn raw(&self) -> Self::Handle;
+}
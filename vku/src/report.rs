@@ -0,0 +1,346 @@
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use std::{
+    ffi::{CStr, CString},
+    fmt,
+};
+
+use ash::vk;
+
+/// Formats a Vulkan version number as `major.minor.patch`
+fn version(v: u32) -> String {
+    let (major, minor, patch) = (
+        vk::api_version_major(v),
+        vk::api_version_minor(v),
+        vk::api_version_patch(v),
+    );
+    format!("{major}.{minor}.{patch}")
+}
+
+/// Converts a list of Vulkan names into strings
+fn names(names: &[CString]) -> Vec<String> {
+    names
+        .iter()
+        .map(|n| n.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Summary of the physical device a logical device has been created from
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSummary {
+    pub name: String,
+    pub device_type: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub api_version: String,
+    /// The driver version, its encoding is vendor specific
+    pub driver_version: u32,
+    /// The enabled device extensions
+    pub extensions: Vec<String>,
+    /// The queue families for which queues have been created and the number of queues
+    pub queue_families: Vec<(u32, usize)>,
+    /// What the device can do, [`None`] if it couldn't be queried
+    pub capabilities: Option<super::DeviceCapabilities>,
+}
+
+/// Summary of the properties of a swapchain
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapchainSummary {
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub color_space: String,
+    pub image_count: u32,
+    pub present_mode: String,
+    pub transform: String,
+}
+
+/// A snapshot of the Vulkan environment the application is running in,
+/// meant to be attached to bug reports
+///
+/// The [`Display`](fmt::Display) implementation produces a markdown list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvironmentReport {
+    /// The Vulkan API version requested by the instance
+    pub api_version: String,
+    /// The enabled instance layers
+    pub layers: Vec<String>,
+    /// The enabled instance extensions
+    pub extensions: Vec<String>,
+    /// The selected device, if a logical device has been created
+    pub device: Option<DeviceSummary>,
+    /// The swapchain details, if a swapchain has been created
+    pub swapchain: Option<SwapchainSummary>,
+}
+
+/// Collects the instance-level information of the report
+pub fn collect<I: super::InstanceHolder>(instance: &I) -> EnvironmentReport {
+    let info = instance.vk_info();
+    EnvironmentReport {
+        api_version: version(info.api_version),
+        layers: names(&info.layers),
+        extensions: names(&info.extensions),
+        device: None,
+        swapchain: None,
+    }
+}
+
+/// Collects the instance and device information of the report
+pub fn collect_device<D: super::InstanceHolder + super::DeviceHolder>(
+    device: &D,
+) -> EnvironmentReport {
+    let info = device.vk_device_info();
+    let props = unsafe {
+        device
+            .vk_backend()
+            .get_physical_device_properties(info.physical_device)
+    };
+    // SAFETY: The name is a null terminated string written by the Vulkan driver
+    let name = unsafe { CStr::from_ptr(props.device_name.as_ptr()) };
+    EnvironmentReport {
+        device: Some(DeviceSummary {
+            name: name.to_string_lossy().into_owned(),
+            device_type: format!("{:?}", props.device_type),
+            vendor_id: props.vendor_id,
+            device_id: props.device_id,
+            api_version: version(props.api_version),
+            driver_version: props.driver_version,
+            extensions: names(&info.extensions),
+            queue_families: info
+                .queue_families
+                .iter()
+                .map(|fam| (fam.index, fam.priorities.len()))
+                .collect(),
+            capabilities: super::PhysicalDevRef::new(device, info.physical_device)
+                .capabilities()
+                .ok(),
+        }),
+        ..collect(device)
+    }
+}
+
+#[cfg(feature = "surface")]
+impl EnvironmentReport {
+    /// Adds the details of the swapchain to the report
+    pub fn with_swapchain<I: super::SurfaceHolder + super::DeviceHolder>(
+        self,
+        swapchain: &vku::Swapchain<I>,
+    ) -> Self {
+        let details = swapchain.details();
+        Self {
+            swapchain: Some(SwapchainSummary {
+                width: details.extent.width,
+                height: details.extent.height,
+                format: format!("{:?}", details.format),
+                color_space: format!("{:?}", details.color_space),
+                image_count: details.count,
+                present_mode: format!("{:?}", details.present_mode),
+                transform: format!("{:?}", details.transform),
+            }),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for EnvironmentReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "#### Vulkan environment")?;
+        writeln!(f)?;
+        writeln!(f, "- Instance API version: {}", self.api_version)?;
+        writeln!(f, "- Layers: {}", self.layers.join(", "))?;
+        writeln!(f, "- Instance extensions: {}", self.extensions.join(", "))?;
+        if let Some(dev) = &self.device {
+            writeln!(
+                f,
+                "- Device: {} ({}, vendor {:#06x}, device {:#06x})",
+                dev.name, dev.device_type, dev.vendor_id, dev.device_id
+            )?;
+            writeln!(f, "- Device API version: {}", dev.api_version)?;
+            writeln!(f, "- Driver version: {:#x}", dev.driver_version)?;
+            writeln!(f, "- Device extensions: {}", dev.extensions.join(", "))?;
+            let families: Vec<_> = dev
+                .queue_families
+                .iter()
+                .map(|(index, count)| format!("{index} ({count} queues)"))
+                .collect();
+            writeln!(f, "- Queue families: {}", families.join(", "))?;
+            if let Some(caps) = &dev.capabilities {
+                let mut features = vec![];
+                let flags = [
+                    (caps.compute_queue, "compute"),
+                    (
+                        caps.dedicated_transfer_family.is_some(),
+                        "dedicated transfer",
+                    ),
+                    (caps.bc_compression, "BC"),
+                    (caps.etc2_compression, "ETC2"),
+                    (caps.astc_compression, "ASTC"),
+                    (caps.ray_tracing, "ray tracing"),
+                    (caps.mesh_shader, "mesh shader"),
+                ];
+                features.extend(flags.iter().filter(|(on, _)| *on).map(|(_, name)| *name));
+                if let Some(present) = caps.can_present {
+                    features.push(if present { "present" } else { "no present" });
+                }
+                writeln!(
+                    f,
+                    "- Capabilities: {}; max texture {}, max MSAA {}x, push constants {} B",
+                    features.join(", "),
+                    caps.max_texture_size,
+                    caps.max_msaa_samples,
+                    caps.max_push_constants_size
+                )?;
+            }
+        }
+        if let Some(sc) = &self.swapchain {
+            writeln!(
+                f,
+                "- Swapchain: {}x{}, {} images, {} {}, {}, {}",
+                sc.width,
+                sc.height,
+                sc.image_count,
+                sc.format,
+                sc.color_space,
+                sc.present_mode,
+                sc.transform
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance_report() -> EnvironmentReport {
+        EnvironmentReport {
+            api_version: version(vk::API_VERSION_1_2),
+            layers: vec!["VK_LAYER_KHRONOS_validation".to_owned()],
+            extensions: vec!["VK_KHR_surface".to_owned(), "VK_KHR_xcb_surface".to_owned()],
+            device: None,
+            swapchain: None,
+        }
+    }
+
+    fn device_summary(capabilities: Option<crate::DeviceCapabilities>) -> DeviceSummary {
+        DeviceSummary {
+            name: "llvmpipe (LLVM 15.0.7, 256 bits)".to_owned(),
+            device_type: format!("{:?}", vk::PhysicalDeviceType::CPU),
+            vendor_id: 0x10005,
+            device_id: 0,
+            api_version: version(vk::make_api_version(0, 1, 3, 238)),
+            driver_version: vk::make_api_version(0, 0, 0, 1),
+            extensions: vec!["VK_KHR_swapchain".to_owned()],
+            queue_families: vec![(0, 1)],
+            capabilities,
+        }
+    }
+
+    #[test]
+    fn instance_only() {
+        assert_eq!(
+            instance_report().to_string(),
+            "\
+#### Vulkan environment
+
+- Instance API version: 1.2.0
+- Layers: VK_LAYER_KHRONOS_validation
+- Instance extensions: VK_KHR_surface, VK_KHR_xcb_surface
+"
+        );
+    }
+
+    #[test]
+    fn empty_lists() {
+        let report = EnvironmentReport {
+            layers: vec![],
+            extensions: vec![],
+            ..instance_report()
+        };
+        assert_eq!(
+            report.to_string(),
+            "\
+#### Vulkan environment
+
+- Instance API version: 1.2.0
+- Layers: 
+- Instance extensions: 
+"
+        );
+    }
+
+    #[test]
+    fn device_without_capabilities() {
+        let report = EnvironmentReport {
+            device: Some(device_summary(None)),
+            ..instance_report()
+        };
+        assert_eq!(
+            report.to_string(),
+            "\
+#### Vulkan environment
+
+- Instance API version: 1.2.0
+- Layers: VK_LAYER_KHRONOS_validation
+- Instance extensions: VK_KHR_surface, VK_KHR_xcb_surface
+- Device: llvmpipe (LLVM 15.0.7, 256 bits) (CPU, vendor 0x10005, device 0x0000)
+- Device API version: 1.3.238
+- Driver version: 0x1
+- Device extensions: VK_KHR_swapchain
+- Queue families: 0 (1 queues)
+"
+        );
+    }
+
+    #[test]
+    fn full_report() {
+        let capabilities = crate::DeviceCapabilities {
+            can_present: Some(true),
+            compute_queue: true,
+            dedicated_transfer_family: None,
+            max_texture_size: 16384,
+            max_msaa_samples: 4,
+            bc_compression: true,
+            etc2_compression: false,
+            astc_compression: false,
+            ray_tracing: false,
+            mesh_shader: false,
+            max_push_constants_size: 256,
+        };
+        let report = EnvironmentReport {
+            device: Some(device_summary(Some(capabilities))),
+            swapchain: Some(SwapchainSummary {
+                width: 800,
+                height: 600,
+                format: format!("{:?}", vk::Format::B8G8R8A8_SRGB),
+                color_space: format!("{:?}", vk::ColorSpaceKHR::SRGB_NONLINEAR),
+                image_count: 3,
+                present_mode: format!("{:?}", vk::PresentModeKHR::FIFO),
+                transform: format!("{:?}", vk::SurfaceTransformFlagsKHR::IDENTITY),
+            }),
+            ..instance_report()
+        };
+        assert_eq!(
+            report.to_string(),
+            "\
+#### Vulkan environment
+
+- Instance API version: 1.2.0
+- Layers: VK_LAYER_KHRONOS_validation
+- Instance extensions: VK_KHR_surface, VK_KHR_xcb_surface
+- Device: llvmpipe (LLVM 15.0.7, 256 bits) (CPU, vendor 0x10005, device 0x0000)
+- Device API version: 1.3.238
+- Driver version: 0x1
+- Device extensions: VK_KHR_swapchain
+- Queue families: 0 (1 queues)
+- Capabilities: compute, BC, present; max texture 16384, max MSAA 4x, push constants 256 B
+- Swapchain: 800x600, 3 images, B8G8R8A8_SRGB SRGB_NONLINEAR, FIFO, IDENTITY
+"
+        );
+    }
+}
@@ -1,2 +1,293 @@
-pub type Error = ash::vk::Result;
-pub type Result<T> = ash::prelude::VkResult<T>;
+use std::fmt;
+
+use ash::vk::{self, Handle};
+
+/// The operation that was being performed when an [`Error`] was generated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// `vkEnumerateInstanceVersion`
+    EnumerateInstanceVersion,
+    /// `vkCreateInstance`
+    CreateInstance,
+    /// `vkCreateDebugUtilsMessengerEXT`
+    CreateDebugUtilsMessenger,
+    /// `vkEnumerateInstanceExtensionProperties`
+    EnumerateInstanceExtensions,
+    /// Looking up the instance extensions required by a window system
+    EnumerateSurfaceExtensions,
+    /// Creating a surface for a window through the platform specific function
+    CreateSurface,
+    /// `vkEnumeratePhysicalDevices`
+    EnumeratePhysicalDevices,
+    /// `vkEnumeratePhysicalDeviceGroups`
+    EnumeratePhysicalDeviceGroups,
+    /// `vkEnumerateDeviceExtensionProperties`
+    EnumerateDeviceExtensions,
+    /// `vkGetPhysicalDeviceSurfaceSupportKHR`
+    GetSurfaceSupport,
+    /// `vkGetPhysicalDeviceSurfaceCapabilitiesKHR`
+    GetSurfaceCapabilities,
+    /// `vkGetPhysicalDeviceSurfaceFormatsKHR`
+    GetSurfaceFormats,
+    /// `vkGetPhysicalDeviceSurfacePresentModesKHR`
+    GetSurfacePresentModes,
+    /// `vkCreateDevice`
+    CreateDevice,
+    /// `vkCreateSwapchainKHR`
+    CreateSwapchain,
+    /// `vkGetSwapchainImagesKHR`
+    GetSwapchainImages,
+    /// `vkAcquireNextImageKHR`
+    AcquireNextImage,
+    /// `vkQueuePresentKHR`
+    QueuePresent,
+    /// `vkCreateCommandPool`
+    CreateCommandPool,
+    /// `vkQueueSubmit`
+    QueueSubmit,
+    /// `vkCreateShaderModule`
+    CreateShaderModule,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::EnumerateInstanceVersion => "enumerating instance version",
+            Self::CreateInstance => "creating instance",
+            Self::CreateDebugUtilsMessenger => "creating debug utils messenger",
+            Self::EnumerateInstanceExtensions => "enumerating instance extensions",
+            Self::EnumerateSurfaceExtensions => "enumerating surface extensions",
+            Self::CreateSurface => "creating surface",
+            Self::EnumeratePhysicalDevices => "enumerating physical devices",
+            Self::EnumeratePhysicalDeviceGroups => "enumerating physical device groups",
+            Self::EnumerateDeviceExtensions => "enumerating device extensions",
+            Self::GetSurfaceSupport => "querying surface support",
+            Self::GetSurfaceCapabilities => "querying surface capabilities",
+            Self::GetSurfaceFormats => "querying surface formats",
+            Self::GetSurfacePresentModes => "querying surface present modes",
+            Self::CreateDevice => "creating logical device",
+            Self::CreateSwapchain => "creating swapchain",
+            Self::GetSwapchainImages => "getting swapchain images",
+            Self::AcquireNextImage => "acquiring next swapchain image",
+            Self::QueuePresent => "presenting swapchain image",
+            Self::CreateCommandPool => "creating command pool",
+            Self::QueueSubmit => "submitting to queue",
+            Self::CreateShaderModule => "creating shader module",
+        })
+    }
+}
+
+/// A single link of the context chain of an [`Error`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Context {
+    /// The operation that failed
+    pub operation: Operation,
+    /// The type and the raw handle of the object on which the operation was performed, if any
+    pub parent: Option<(vk::ObjectType, u64)>,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some((ty, raw)) = self.parent {
+            write!(f, " [{ty:?} {raw:#x}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// The error type returned by all the [vku](crate) functions
+///
+/// New variants may be added in any release, so matches must have a wildcard arm
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// An error directly returned by a Vulkan function
+    Vulkan(vk::Result),
+    /// The Vulkan implementation doesn't support creating surfaces for the window system
+    UnsupportedWindowSystem(&'static str),
+    /// The window or display handle can't be accessed at the moment,
+    /// e.g. on Android when the application is paused
+    WindowHandleUnavailable,
+    /// The instance extensions required by the window system are not available
+    MissingInstanceExtensions {
+        /// The name of the window system, e.g. `"Wayland"`
+        window_system: &'static str,
+        /// The extensions that are not available
+        extensions: Vec<std::ffi::CString>,
+    },
+    /// The queue families requested for a logical device are not valid
+    InvalidQueueInfos {
+        /// Which of the requirements is not met
+        reason: super::queue_family::InvalidQueueInfos,
+    },
+    /// A logical device can't be created over more than one physical device, since the instance
+    /// has been created neither for Vulkan 1.1 nor with `VK_KHR_device_group_creation`
+    DeviceGroupsUnsupported,
+    /// The instance didn't enumerate any physical device
+    ///
+    /// `hint` describes the problems found with the driver manifests pointed by
+    /// the `VK_DRIVER_FILES` or `VK_ICD_FILENAMES` environment variables, if any
+    NoVulkanDevices {
+        /// What's wrong with the driver manifests, if anything
+        hint: Option<String>,
+    },
+    /// The device forced through [`DeviceCriteria::force_index`](super::DeviceCriteria::force_index)
+    /// doesn't exist or is not suitable, `reason` tells which
+    ForcedDeviceRejected {
+        /// The forced index
+        index: usize,
+        /// Why the device can't be selected
+        reason: String,
+    },
+    /// The index of the selected physical device is out of the list of available devices
+    DeviceIndexOutOfRange {
+        /// The selected index
+        index: usize,
+        /// The number of available devices
+        count: usize,
+    },
+    /// The details of a swapchain are not valid for the surface
+    InvalidImageDetails(super::swapchain::InvalidDetails),
+    /// An error that happened while performing the operation described by the context
+    Context {
+        /// The operation that was being performed
+        context: Context,
+        /// The error the operation failed with
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Returns the Vulkan result code that caused this error, if any
+    pub fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            Self::Vulkan(result) => Some(*result),
+            Self::Context { source, .. } => source.vk_result(),
+            Self::UnsupportedWindowSystem(_)
+            | Self::WindowHandleUnavailable
+            | Self::MissingInstanceExtensions { .. }
+            | Self::InvalidQueueInfos { .. }
+            | Self::DeviceGroupsUnsupported
+            | Self::NoVulkanDevices { .. }
+            | Self::ForcedDeviceRejected { .. }
+            | Self::DeviceIndexOutOfRange { .. }
+            | Self::InvalidImageDetails(_) => None,
+        }
+    }
+
+    /// Returns an iterator over the context chain of this error,
+    /// starting from the outermost operation
+    pub fn contexts(&self) -> impl Iterator<Item = &Context> {
+        std::iter::successors(Some(self), |err| match err {
+            Self::Context { source, .. } => Some(source),
+            _ => None,
+        })
+        .filter_map(|err| match err {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        })
+    }
+}
+
+impl From<vk::Result> for Error {
+    fn from(result: vk::Result) -> Self {
+        Self::Vulkan(result)
+    }
+}
+
+impl From<super::swapchain::InvalidDetails> for Error {
+    fn from(invalid: super::swapchain::InvalidDetails) -> Self {
+        Self::InvalidImageDetails(invalid)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vulkan(result) => write!(f, "VK_{result:?}"),
+            Self::UnsupportedWindowSystem(ws) => write!(f, "unsupported window system {ws}"),
+            Self::WindowHandleUnavailable => f.write_str("the window handle is unavailable"),
+            Self::MissingInstanceExtensions {
+                window_system,
+                extensions,
+            } => {
+                write!(f, "missing instance extensions for {window_system}:")?;
+                extensions
+                    .iter()
+                    .try_for_each(|ext| write!(f, " {}", ext.to_string_lossy()))
+            }
+            Self::InvalidQueueInfos { reason } => write!(f, "invalid queue families: {reason}"),
+            Self::DeviceGroupsUnsupported => {
+                f.write_str("device groups require Vulkan 1.1 or VK_KHR_device_group_creation")
+            }
+            Self::NoVulkanDevices { hint } => {
+                f.write_str(
+                    "no Vulkan devices found, check that a Vulkan driver (ICD) is installed \
+                    and, when running in a container, that the GPU is passed through",
+                )?;
+                match hint {
+                    Some(hint) => write!(f, " ({hint})"),
+                    None => Ok(()),
+                }
+            }
+            Self::ForcedDeviceRejected { index, reason } => {
+                write!(f, "the forced device {index} can't be selected: {reason}")
+            }
+            Self::DeviceIndexOutOfRange { index, count } => {
+                write!(
+                    f,
+                    "device index {index} out of the {count} physical devices"
+                )
+            }
+            Self::InvalidImageDetails(invalid) => write!(f, "invalid swapchain details: {invalid}"),
+            Self::Context { context, source } => match **source {
+                Self::Context { .. } => write!(f, "{context} → {source}"),
+                _ => write!(f, "{context}: {source}"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Extension trait used to attach an [`Operation`] to the error of a [`Result`]
+pub trait ErrorContext<T> {
+    /// Wraps the error with the provided operation
+    fn context(self, operation: Operation) -> Result<T>;
+
+    /// Wraps the error with the provided operation performed on the `parent` object
+    fn context_of<H: Handle>(self, operation: Operation, parent: H) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ErrorContext<T> for std::result::Result<T, E> {
+    fn context(self, operation: Operation) -> Result<T> {
+        self.map_err(|err| Error::Context {
+            context: Context {
+                operation,
+                parent: None,
+            },
+            source: Box::new(err.into()),
+        })
+    }
+
+    fn context_of<H: Handle>(self, operation: Operation, parent: H) -> Result<T> {
+        self.map_err(|err| Error::Context {
+            context: Context {
+                operation,
+                parent: Some((H::TYPE, parent.as_raw())),
+            },
+            source: Box::new(err.into()),
+        })
+    }
+}
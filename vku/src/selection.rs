@@ -0,0 +1,757 @@
+use std::ffi::CStr;
+
+use ash::vk;
+
+/// Identifies a physical device across runs of the application,
+/// so that the device picked by the user can be stored and selected again
+///
+/// Check [`vku::PhysicalDevList::find_matching`](super::PhysicalDevList::find_matching)
+/// for how a device is matched
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSelector {
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub device_name: String,
+}
+
+impl DeviceSelector {
+    /// Creates the selector that matches the provided device
+    pub fn of<I: super::InstanceHolder>(dev: super::PhysicalDevRef<I>) -> Self {
+        let props = dev.properties();
+        // SAFETY: The name is a null terminated string written by the Vulkan driver
+        let name = unsafe { CStr::from_ptr(props.device_name.as_ptr()) };
+        Self {
+            vendor_id: props.vendor_id,
+            device_id: props.device_id,
+            device_name: name.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+/// A description of a physical device meant to be shown to the user,
+/// e.g. in a device picker
+///
+/// Returned by [`vku::PhysicalDevList::describe_all`](super::PhysicalDevList::describe_all)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceDescription {
+    /// The index of the device in the [`PhysicalDevList`](super::PhysicalDevList)
+    pub index: usize,
+    /// The name reported by the driver
+    pub name: String,
+    /// The type of the device
+    #[cfg_attr(feature = "serde", serde(with = "raw::device_type"))]
+    pub device_type: vk::PhysicalDeviceType,
+    /// The total size in bytes of the device local memory heaps
+    pub vram: u64,
+    /// The rank given by the criteria, [`None`] if the device is not suitable
+    pub rank: Option<u32>,
+    /// Why the device is not suitable, empty if it is
+    pub reasons: Vec<String>,
+}
+
+impl DeviceDescription {
+    /// Returns whether or not the device satisfies all the criteria
+    pub fn is_suitable(&self) -> bool {
+        self.reasons.is_empty()
+    }
+}
+
+/// Environment variable that forces [`select_best`](super::PhysicalDevList::select_best)
+/// to pick the device at the given index, applied by [`DeviceCriteria::with_env_overrides`]
+pub const FORCE_DEVICE_INDEX_VAR: &str = "VKU_FORCE_DEVICE_INDEX";
+
+/// Environment variable that, when set to `1` or `true`, allows software devices
+/// (e.g. lavapipe or SwiftShader) to be selected, applied by [`DeviceCriteria::with_env_overrides`]
+pub const ALLOW_SOFTWARE_VAR: &str = "VKU_ALLOW_SOFTWARE";
+
+/// The criteria used by [`select_best`](super::PhysicalDevList::select_best)
+/// to rank the physical devices
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceCriteria {
+    /// The accepted device types, in order of preference
+    #[cfg_attr(feature = "serde", serde(with = "raw::device_types"))]
+    pub device_types: Vec<vk::PhysicalDeviceType>,
+    /// Whether or not devices of type [`CPU`](vk::PhysicalDeviceType::CPU) are acceptable,
+    /// even if listed in `device_types`
+    pub allow_software: bool,
+    /// The index of the device that must be selected, ignoring the device types
+    ///
+    /// It's a runtime override, so it's never serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub force_index: Option<usize>,
+}
+
+impl Default for DeviceCriteria {
+    /// Accepts every device type, preferring in order discrete, integrated and virtual GPUs,
+    /// then software devices (when allowed) and the other types
+    fn default() -> Self {
+        use vk::PhysicalDeviceType as Ty;
+        Self {
+            device_types: vec![
+                Ty::DISCRETE_GPU,
+                Ty::INTEGRATED_GPU,
+                Ty::VIRTUAL_GPU,
+                Ty::CPU,
+                Ty::OTHER,
+            ],
+            allow_software: false,
+            force_index: None,
+        }
+    }
+}
+
+/// Why a device has been rejected by a [`DeviceCriteria`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// The device type is not in the accepted list
+    DeviceType(vk::PhysicalDeviceType),
+    /// The device is a software device, which are not allowed
+    Software,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DeviceType(ty) => write!(f, "device type {ty:?} ranked below threshold"),
+            Self::Software => f.write_str("software devices are not allowed"),
+        }
+    }
+}
+
+impl DeviceCriteria {
+    /// Sets the accepted device types, in order of preference
+    pub fn device_types(self, device_types: Vec<vk::PhysicalDeviceType>) -> Self {
+        Self {
+            device_types,
+            ..self
+        }
+    }
+
+    /// Sets whether or not software devices are acceptable
+    pub fn allow_software(self, allow_software: bool) -> Self {
+        Self {
+            allow_software,
+            ..self
+        }
+    }
+
+    /// Sets the index of the device that must be selected, ignoring the device types
+    pub fn force_index(self, force_index: Option<usize>) -> Self {
+        Self {
+            force_index,
+            ..self
+        }
+    }
+
+    /// Applies the overrides of the [`ALLOW_SOFTWARE_VAR`] and [`FORCE_DEVICE_INDEX_VAR`]
+    /// environment variables
+    ///
+    /// Values that can't be parsed are ignored
+    pub fn with_env_overrides(self) -> Self {
+        self.with_overrides(
+            std::env::var(ALLOW_SOFTWARE_VAR).ok().as_deref(),
+            std::env::var(FORCE_DEVICE_INDEX_VAR).ok().as_deref(),
+        )
+    }
+
+    /// Applies the values of the environment variables read by [`Self::with_env_overrides`]
+    fn with_overrides(self, allow_software: Option<&str>, force_index: Option<&str>) -> Self {
+        let this = match allow_software.and_then(parse_flag) {
+            Some(allow) => self.allow_software(allow),
+            None => self,
+        };
+        match force_index.and_then(parse_index) {
+            Some(index) => this.force_index(Some(index)),
+            None => this,
+        }
+    }
+
+    /// Returns the rank of a device of type `device_type`, the higher the better,
+    /// or the reason why the device is not acceptable
+    pub fn check(&self, device_type: vk::PhysicalDeviceType) -> Result<u32, Rejection> {
+        if device_type == vk::PhysicalDeviceType::CPU && !self.allow_software {
+            return Err(Rejection::Software);
+        }
+        let pos = self
+            .device_types
+            .iter()
+            .position(|&ty| ty == device_type)
+            .ok_or(Rejection::DeviceType(device_type))?;
+        Ok((self.device_types.len() - pos) as u32)
+    }
+
+    /// Returns the rank of a device of type `device_type`, the higher the better,
+    /// or [`None`] if the device is not acceptable
+    pub fn rank(&self, device_type: vk::PhysicalDeviceType) -> Option<u32> {
+        self.check(device_type).ok()
+    }
+}
+
+/// Returns the index of the best device according to `criteria`, given the types of the
+/// devices in the order they have been enumerated
+///
+/// `is_suitable` is called with the index of the devices accepted by the `criteria`,
+/// the first enumerated wins the ties. This is the policy of
+/// [`select_best`](super::PhysicalDevList::select_best), apart from the forced index
+pub(crate) fn best_device(
+    criteria: &DeviceCriteria,
+    device_types: impl IntoIterator<Item = vk::PhysicalDeviceType>,
+    mut is_suitable: impl FnMut(usize) -> bool,
+) -> Option<usize> {
+    device_types
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, ty)| Some((i, criteria.rank(ty)?)))
+        .filter(|&(i, _)| is_suitable(i))
+        // Only replace the best device with one of a strictly higher rank
+        // so that the first one enumerated wins the ties
+        .fold(None, |best, (i, rank)| match best {
+            Some((_, best_rank)) if best_rank >= rank => best,
+            _ => Some((i, rank)),
+        })
+        .map(|(i, _)| i)
+}
+
+/// What a physical device reports about itself, used to describe it
+#[derive(Clone, Debug)]
+pub(crate) struct DeviceReport {
+    /// The name reported by the driver
+    pub name: String,
+    /// The type of the device
+    pub device_type: vk::PhysicalDeviceType,
+    /// The memory heaps of the device
+    pub memory_heaps: Vec<vk::MemoryHeap>,
+    /// Whether or not any queue family can present to the window, [`None`] without a window,
+    /// or the error of the query
+    pub can_present: Result<Option<bool>, String>,
+    /// Whether or not the device supports `VK_KHR_swapchain`, [`None`] if it's not required
+    pub supports_swapchain: Option<bool>,
+}
+
+/// Describes the devices `reports`, given in the order they have been enumerated,
+/// along with the reasons for which they don't satisfy `criteria`
+///
+/// The suitable devices come first, sorted by rank; devices with the same rank
+/// keep the order they have been enumerated in. This is the policy of
+/// [`describe_all`](super::PhysicalDevList::describe_all)
+pub(crate) fn describe_devices(
+    criteria: &DeviceCriteria,
+    reports: impl IntoIterator<Item = DeviceReport>,
+) -> Vec<DeviceDescription> {
+    let mut descriptions: Vec<_> = reports
+        .into_iter()
+        .enumerate()
+        .map(|(index, report)| {
+            let vram = report
+                .memory_heaps
+                .iter()
+                .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+                .map(|heap| heap.size)
+                .sum();
+
+            let mut reasons = vec![];
+            let rank = criteria
+                .check(report.device_type)
+                .map_err(|rejection| reasons.push(rejection.to_string()))
+                .ok();
+            match report.can_present {
+                Ok(Some(false)) => {
+                    reasons.push("no queue family can present to the window".to_owned())
+                }
+                Ok(_) => {}
+                Err(err) => reasons.push(format!("failed to query the device: {err}")),
+            }
+            if report.supports_swapchain == Some(false) {
+                let name = ash::extensions::khr::Swapchain::name().to_string_lossy();
+                reasons.push(format!("missing extension {name}"));
+            }
+
+            DeviceDescription {
+                index,
+                name: report.name,
+                device_type: report.device_type,
+                vram,
+                rank: rank.filter(|_| reasons.is_empty()),
+                reasons,
+            }
+        })
+        .collect();
+    // The sort is stable, so the enumeration order is kept among equals
+    descriptions.sort_by_key(|desc| std::cmp::Reverse(desc.rank));
+    descriptions
+}
+
+/// Parses the value of a boolean environment variable
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses the value of an environment variable containing an index
+fn parse_index(value: &str) -> Option<usize> {
+    value.trim().parse().ok()
+}
+
+/// What a physical device reports about the surface of the window,
+/// used to find out which device is driving it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PresentCandidate {
+    /// Whether or not any queue family of the device can present to the surface
+    pub supports_present: bool,
+    /// The `current_extent` of the surface capabilities reported by the device
+    pub current_extent: vk::Extent2D,
+}
+
+/// Returns the index of the candidate that is most likely driving a window of size `window_extent`
+///
+/// On systems with hybrid graphics every device may be able to present to the surface,
+/// but only the one connected to the monitor reports the actual size of the window.
+/// So the first device that can present and whose current extent is either the window size
+/// or the special value `0xFFFFFFFF` (the size is determined by the swapchain) is preferred,
+/// otherwise the first device that can present is returned.
+pub fn pick_presenting_device(
+    candidates: &[PresentCandidate],
+    window_extent: vk::Extent2D,
+) -> Option<usize> {
+    let matches = |extent: vk::Extent2D| extent == window_extent || extent.width == u32::MAX;
+    candidates
+        .iter()
+        .position(|c| c.supports_present && matches(c.current_extent))
+        .or_else(|| candidates.iter().position(|c| c.supports_present))
+}
+
+/// The user preferences about the swapchain
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapchainPreferences {
+    /// The present modes in order of preference
+    #[cfg_attr(feature = "serde", serde(with = "raw::present_modes"))]
+    pub present_modes: Vec<vk::PresentModeKHR>,
+    /// The surface formats in order of preference
+    #[cfg_attr(feature = "serde", serde(with = "raw::formats"))]
+    pub format_preference: Vec<vk::SurfaceFormatKHR>,
+    /// The number of images the swapchain should have,
+    /// [`None`] to let the application decide
+    pub image_count: Option<u32>,
+}
+
+impl Default for SwapchainPreferences {
+    /// Prefers [`MAILBOX`](vk::PresentModeKHR::MAILBOX) as present mode and
+    /// `R8G8B8A8_SRGB` with the `SRGB_NONLINEAR` color space as format
+    fn default() -> Self {
+        Self {
+            present_modes: vec![vk::PresentModeKHR::MAILBOX],
+            format_preference: vec![vk::SurfaceFormatKHR {
+                format: vk::Format::R8G8B8A8_SRGB,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            }],
+            image_count: None,
+        }
+    }
+}
+
+impl SwapchainPreferences {
+    /// Returns the most preferred among the `available` present modes
+    ///
+    /// When none of them is available [`FIFO`](vk::PresentModeKHR::FIFO) is returned
+    /// since it's always supported
+    pub fn choose_present_mode(&self, available: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
+        self.present_modes
+            .iter()
+            .copied()
+            .find(|mode| available.contains(mode))
+            .unwrap_or(vk::PresentModeKHR::FIFO)
+    }
+
+    /// Returns the most preferred among the `available` formats
+    ///
+    /// When none of them is available the first available one is returned,
+    /// or [`None`] if `available` is empty
+    pub fn choose_format(
+        &self,
+        available: &[vk::SurfaceFormatKHR],
+    ) -> Option<vk::SurfaceFormatKHR> {
+        self.format_preference
+            .iter()
+            .copied()
+            .find(|fmt| available.contains(fmt))
+            .or_else(|| available.first().copied())
+    }
+}
+
+/// Serialization of the Vulkan enums as their raw values
+#[cfg(feature = "serde")]
+mod raw {
+    pub mod present_modes {
+        use ash::vk;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(v: &[vk::PresentModeKHR], s: S) -> Result<S::Ok, S::Error> {
+            let raw: Vec<_> = v.iter().map(|m| m.as_raw()).collect();
+            raw.serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            d: D,
+        ) -> Result<Vec<vk::PresentModeKHR>, D::Error> {
+            let raw = Vec::<i32>::deserialize(d)?;
+            Ok(raw.into_iter().map(vk::PresentModeKHR::from_raw).collect())
+        }
+    }
+
+    pub mod device_types {
+        use ash::vk;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            v: &[vk::PhysicalDeviceType],
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            let raw: Vec<_> = v.iter().map(|t| t.as_raw()).collect();
+            raw.serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            d: D,
+        ) -> Result<Vec<vk::PhysicalDeviceType>, D::Error> {
+            let raw = Vec::<i32>::deserialize(d)?;
+            Ok(raw
+                .into_iter()
+                .map(vk::PhysicalDeviceType::from_raw)
+                .collect())
+        }
+    }
+
+    pub mod device_type {
+        use ash::vk;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            v: &vk::PhysicalDeviceType,
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            v.as_raw().serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            d: D,
+        ) -> Result<vk::PhysicalDeviceType, D::Error> {
+            i32::deserialize(d).map(vk::PhysicalDeviceType::from_raw)
+        }
+    }
+
+    pub mod formats {
+        use ash::vk;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            v: &[vk::SurfaceFormatKHR],
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            let raw: Vec<_> = v
+                .iter()
+                .map(|f| (f.format.as_raw(), f.color_space.as_raw()))
+                .collect();
+            raw.serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            d: D,
+        ) -> Result<Vec<vk::SurfaceFormatKHR>, D::Error> {
+            let raw = Vec::<(i32, i32)>::deserialize(d)?;
+            Ok(raw
+                .into_iter()
+                .map(|(format, color_space)| vk::SurfaceFormatKHR {
+                    format: vk::Format::from_raw(format),
+                    color_space: vk::ColorSpaceKHR::from_raw(color_space),
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: vk::Extent2D = vk::Extent2D {
+        width: 800,
+        height: 600,
+    };
+
+    fn candidate(supports_present: bool, width: u32, height: u32) -> PresentCandidate {
+        PresentCandidate {
+            supports_present,
+            current_extent: vk::Extent2D { width, height },
+        }
+    }
+
+    use vk::PhysicalDeviceType as Ty;
+
+    #[test]
+    fn default_criteria_ranks() {
+        let criteria = DeviceCriteria::default();
+        let table = [
+            (Ty::DISCRETE_GPU, Ok(5)),
+            (Ty::INTEGRATED_GPU, Ok(4)),
+            (Ty::VIRTUAL_GPU, Ok(3)),
+            (Ty::CPU, Err(Rejection::Software)),
+            (Ty::OTHER, Ok(1)),
+            (
+                Ty::from_raw(42),
+                Err(Rejection::DeviceType(Ty::from_raw(42))),
+            ),
+        ];
+        for (ty, expected) in table {
+            assert_eq!(criteria.check(ty), expected, "{ty:?}");
+            assert_eq!(criteria.rank(ty), expected.ok(), "{ty:?}");
+        }
+        assert_eq!(criteria.allow_software(true).check(Ty::CPU), Ok(2));
+    }
+
+    #[test]
+    fn custom_criteria_ranks() {
+        let criteria = DeviceCriteria::default()
+            .device_types(vec![Ty::INTEGRATED_GPU, Ty::DISCRETE_GPU])
+            .allow_software(true);
+        let table = [
+            (Ty::INTEGRATED_GPU, Ok(2)),
+            (Ty::DISCRETE_GPU, Ok(1)),
+            (Ty::VIRTUAL_GPU, Err(Rejection::DeviceType(Ty::VIRTUAL_GPU))),
+            // Allowing software devices doesn't accept them if they are not listed
+            (Ty::CPU, Err(Rejection::DeviceType(Ty::CPU))),
+        ];
+        for (ty, expected) in table {
+            assert_eq!(criteria.check(ty), expected, "{ty:?}");
+        }
+    }
+
+    #[test]
+    fn best_device_policy() {
+        let all = |_| true;
+        let default = DeviceCriteria::default();
+        let software = DeviceCriteria::default().allow_software(true);
+        let table: [(&DeviceCriteria, &[Ty], Option<usize>); 8] = [
+            (&default, &[], None),
+            (&default, &[Ty::INTEGRATED_GPU, Ty::DISCRETE_GPU], Some(1)),
+            (&default, &[Ty::DISCRETE_GPU, Ty::INTEGRATED_GPU], Some(0)),
+            // Ties are won by the first device enumerated
+            (
+                &default,
+                &[Ty::CPU, Ty::DISCRETE_GPU, Ty::DISCRETE_GPU],
+                Some(1),
+            ),
+            (&default, &[Ty::VIRTUAL_GPU, Ty::OTHER], Some(0)),
+            (&default, &[Ty::CPU], None),
+            (&software, &[Ty::CPU, Ty::OTHER], Some(0)),
+            (
+                &software,
+                &[Ty::OTHER, Ty::INTEGRATED_GPU, Ty::CPU],
+                Some(1),
+            ),
+        ];
+        for (criteria, types, expected) in table {
+            let best = best_device(criteria, types.iter().copied(), all);
+            assert_eq!(best, expected, "{types:?}");
+        }
+    }
+
+    #[test]
+    fn best_device_skips_unsuitable() {
+        let types = [Ty::DISCRETE_GPU, Ty::INTEGRATED_GPU, Ty::DISCRETE_GPU];
+        let criteria = DeviceCriteria::default();
+        assert_eq!(best_device(&criteria, types, |i| i != 0), Some(2));
+        assert_eq!(best_device(&criteria, types, |i| i == 1), Some(1));
+        assert_eq!(best_device(&criteria, types, |_| false), None);
+    }
+
+    #[test]
+    fn best_device_only_checks_accepted_devices() {
+        let types = [Ty::CPU, Ty::INTEGRATED_GPU, Ty::VIRTUAL_GPU];
+        let criteria = DeviceCriteria::default().device_types(vec![Ty::INTEGRATED_GPU]);
+        let mut checked = vec![];
+        let best = best_device(&criteria, types, |i| {
+            checked.push(i);
+            true
+        });
+        assert_eq!(best, Some(1));
+        assert_eq!(checked, [1]);
+    }
+
+    #[test]
+    fn overrides_from_the_environment() {
+        let criteria = DeviceCriteria::default().with_overrides(Some("1"), Some(" 2 "));
+        assert!(criteria.allow_software);
+        assert_eq!(criteria.force_index, Some(2));
+
+        let criteria = DeviceCriteria::default()
+            .allow_software(true)
+            .with_overrides(Some("off"), None);
+        assert!(!criteria.allow_software);
+        assert_eq!(criteria.force_index, None);
+    }
+
+    #[test]
+    fn invalid_overrides_are_ignored() {
+        let criteria = DeviceCriteria::default()
+            .force_index(Some(1))
+            .with_overrides(Some("maybe"), Some("-1"));
+        assert_eq!(criteria, DeviceCriteria::default().force_index(Some(1)));
+        let criteria = DeviceCriteria::default().with_overrides(Some(""), Some("first"));
+        assert_eq!(criteria, DeviceCriteria::default());
+    }
+
+    #[test]
+    fn presenting_device_matches_window_extent() {
+        // Hybrid graphics: both can present but only the second drives the monitor
+        let candidates = [candidate(true, 0, 0), candidate(true, 800, 600)];
+        assert_eq!(pick_presenting_device(&candidates, WINDOW), Some(1));
+    }
+
+    #[test]
+    fn presenting_device_accepts_undefined_extent() {
+        let candidates = [
+            candidate(true, 1024, 768),
+            candidate(true, u32::MAX, u32::MAX),
+        ];
+        assert_eq!(pick_presenting_device(&candidates, WINDOW), Some(1));
+    }
+
+    #[test]
+    fn presenting_device_skips_devices_that_cant_present() {
+        let candidates = [candidate(false, 800, 600), candidate(true, 1024, 768)];
+        assert_eq!(pick_presenting_device(&candidates, WINDOW), Some(1));
+    }
+
+    #[test]
+    fn presenting_device_prefers_the_first_match() {
+        let candidates = [candidate(true, 800, 600), candidate(true, 800, 600)];
+        assert_eq!(pick_presenting_device(&candidates, WINDOW), Some(0));
+    }
+
+    #[test]
+    fn presenting_device_none_can_present() {
+        let candidates = [candidate(false, 800, 600), candidate(false, 0, 0)];
+        assert_eq!(pick_presenting_device(&candidates, WINDOW), None);
+        assert_eq!(pick_presenting_device(&[], WINDOW), None);
+    }
+
+    fn report(name: &str, device_type: Ty, vram_mib: &[u64]) -> DeviceReport {
+        DeviceReport {
+            name: name.to_owned(),
+            device_type,
+            memory_heaps: vram_mib
+                .iter()
+                .map(|&mib| vk::MemoryHeap {
+                    size: mib << 20,
+                    flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+                })
+                .chain([vk::MemoryHeap {
+                    size: 1 << 30,
+                    flags: vk::MemoryHeapFlags::empty(),
+                }])
+                .collect(),
+            can_present: Ok(None),
+            supports_swapchain: None,
+        }
+    }
+
+    #[test]
+    fn describe_suitable_device() {
+        let descriptions = describe_devices(
+            &DeviceCriteria::default(),
+            [report("gpu", Ty::DISCRETE_GPU, &[256, 768])],
+        );
+        assert_eq!(
+            descriptions,
+            [DeviceDescription {
+                index: 0,
+                name: "gpu".to_owned(),
+                device_type: Ty::DISCRETE_GPU,
+                // Only the device local heaps are counted
+                vram: 1 << 30,
+                rank: Some(5),
+                reasons: vec![],
+            }]
+        );
+        assert!(descriptions[0].is_suitable());
+    }
+
+    #[test]
+    fn describe_reasons() {
+        let cant_present = DeviceReport {
+            can_present: Ok(Some(false)),
+            supports_swapchain: Some(false),
+            ..report("no present", Ty::INTEGRATED_GPU, &[])
+        };
+        let failed = DeviceReport {
+            can_present: Err("device lost".to_owned()),
+            ..report("failed", Ty::DISCRETE_GPU, &[])
+        };
+        let descriptions = describe_devices(
+            &DeviceCriteria::default(),
+            [report("cpu", Ty::CPU, &[]), cant_present, failed],
+        );
+        let reasons: Vec<_> = descriptions
+            .iter()
+            .map(|desc| (desc.name.as_str(), desc.rank, desc.reasons.clone()))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                (
+                    "cpu",
+                    None,
+                    vec!["software devices are not allowed".to_owned()]
+                ),
+                (
+                    "no present",
+                    None,
+                    vec![
+                        "no queue family can present to the window".to_owned(),
+                        "missing extension VK_KHR_swapchain".to_owned(),
+                    ]
+                ),
+                (
+                    "failed",
+                    None,
+                    vec!["failed to query the device: device lost".to_owned()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn describe_order_is_stable() {
+        let reports = [
+            report("unsuitable", Ty::CPU, &[]),
+            report("integrated 0", Ty::INTEGRATED_GPU, &[]),
+            report("discrete 0", Ty::DISCRETE_GPU, &[]),
+            report("integrated 1", Ty::INTEGRATED_GPU, &[]),
+            report("rejected", Ty::from_raw(42), &[]),
+            report("discrete 1", Ty::DISCRETE_GPU, &[]),
+        ];
+        let order: Vec<_> = describe_devices(&DeviceCriteria::default(), reports)
+            .into_iter()
+            .map(|desc| (desc.index, desc.rank))
+            .collect();
+        // Suitable devices by decreasing rank, then the others, in enumeration order
+        assert_eq!(
+            order,
+            [
+                (2, Some(5)),
+                (5, Some(5)),
+                (1, Some(4)),
+                (3, Some(4)),
+                (0, None),
+                (4, None)
+            ]
+        );
+    }
+}
@@ -1,11 +1,179 @@
-use std::marker::PhantomData;
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, CStr},
+    marker::PhantomData,
+};
 
 use ash::{extensions::khr, vk};
 use raw_window_handle as rwh;
 
+use super::{ErrorContext, Operation};
+
 /// Returns the names of the Vulkan extensions required by the provided window handle
-pub fn extensions(window: rwh::RawDisplayHandle) -> super::Result<&'static [*const i8]> {
-    ash_window::enumerate_required_extensions(window)
+pub fn extensions(window: rwh::RawDisplayHandle) -> super::Result<&'static [*const c_char]> {
+    ash_window::enumerate_required_extensions(window).context(Operation::EnumerateSurfaceExtensions)
+}
+
+/// Returns the name of the window system of the display handle, used in the error messages
+pub fn window_system(display: rwh::RawDisplayHandle) -> &'static str {
+    use rwh::RawDisplayHandle as H;
+    match display {
+        H::UiKit(_) => "UIKit",
+        H::AppKit(_) => "AppKit",
+        H::Orbital(_) => "Orbital",
+        H::Xlib(_) => "Xlib",
+        H::Xcb(_) => "XCB",
+        H::Wayland(_) => "Wayland",
+        H::Drm(_) => "DRM",
+        H::Gbm(_) => "GBM",
+        H::Windows(_) => "Win32",
+        H::Web(_) => "Web",
+        H::Android(_) => "Android",
+        H::Haiku(_) => "Haiku",
+        _ => "unknown",
+    }
+}
+
+/// Returns the names of the Vulkan extensions required by the provided window handle,
+/// checking that they are available for the instance
+///
+/// Unlike [`extensions`], an unsupported window system is reported with
+/// [`Error::UnsupportedWindowSystem`](super::Error::UnsupportedWindowSystem)
+/// and the extensions that the Vulkan implementation doesn't provide with
+/// [`Error::MissingInstanceExtensions`](super::Error::MissingInstanceExtensions)
+pub fn extensions_checked(
+    display: rwh::RawDisplayHandle,
+    entry: &ash::Entry,
+) -> super::Result<super::ExtensionList> {
+    let required = extensions_checked_raw(display, entry)?;
+    // SAFETY: ash-window returns pointers to static null terminated strings
+    Ok(unsafe { super::ExtensionList::from_raw(required) })
+}
+
+/// Same as [`extensions_checked`] but returns the names as raw pointers,
+/// which point to static null terminated strings
+pub fn extensions_checked_raw(
+    display: rwh::RawDisplayHandle,
+    entry: &ash::Entry,
+) -> super::Result<&'static [*const c_char]> {
+    let window_system = window_system(display);
+    let required = match ash_window::enumerate_required_extensions(display) {
+        Ok(required) => required,
+        Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT) => {
+            return Err(super::Error::UnsupportedWindowSystem(window_system))
+        }
+        Err(err) => return Err(err).context(Operation::EnumerateSurfaceExtensions),
+    };
+    let available = entry
+        .enumerate_instance_extension_properties(None)
+        .context(Operation::EnumerateInstanceExtensions)?;
+
+    let missing: Vec<_> = required
+        .iter()
+        // SAFETY: ash-window returns pointers to static null terminated strings
+        .map(|&name| unsafe { CStr::from_ptr(name) })
+        .filter(|&name| {
+            !available.iter().any(|prop| {
+                // SAFETY: This pointer was generated by the Vulkan driver
+                unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) == name }
+            })
+        })
+        .map(CStr::to_owned)
+        .collect();
+    match missing.is_empty() {
+        true => Ok(required),
+        false => Err(super::Error::MissingInstanceExtensions {
+            window_system,
+            extensions: missing,
+        }),
+    }
+}
+
+/// What a physical device reports about a surface
+#[derive(Clone, Debug)]
+pub struct SurfaceInfo {
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    pub present_modes: Vec<vk::PresentModeKHR>,
+}
+
+/// Memoizes the capabilities, formats and present modes of each physical device for a surface
+///
+/// The capabilities change when the window is resized or rotated, so the cache must be
+/// invalidated with [`refresh`] when that happens.
+/// [`vku::Swapchain::recreate_checked`](super::Swapchain::recreate_checked) and [`rebind`]
+/// do it automatically.
+#[derive(Debug, Default)]
+pub struct SurfaceInfoCache {
+    infos: RefCell<HashMap<vk::PhysicalDevice, SurfaceInfo>>,
+}
+
+impl SurfaceInfoCache {
+    /// Returns the info for `device`, querying it if it's not cached
+    ///
+    /// # Safety
+    ///
+    /// `device` must support `surface`
+    unsafe fn get(
+        &self,
+        fns: &dyn super::backend::SurfaceBackend,
+        surface: vk::SurfaceKHR,
+        device: vk::PhysicalDevice,
+    ) -> super::Result<SurfaceInfo> {
+        if let Some(info) = self.infos.borrow().get(&device) {
+            return Ok(info.clone());
+        }
+        let info = SurfaceInfo {
+            capabilities: fns
+                .get_physical_device_surface_capabilities(device, surface)
+                .context_of(Operation::GetSurfaceCapabilities, device)?,
+            formats: fns
+                .get_physical_device_surface_formats(device, surface)
+                .context_of(Operation::GetSurfaceFormats, device)?,
+            present_modes: fns
+                .get_physical_device_surface_present_modes(device, surface)
+                .context_of(Operation::GetSurfacePresentModes, device)?,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?device, ?info, "surface info");
+        self.infos.borrow_mut().insert(device, info.clone());
+        Ok(info)
+    }
+
+    /// Drops all the cached info
+    fn clear(&self) {
+        self.infos.borrow_mut().clear();
+    }
+}
+
+/// Returns the [`SurfaceInfo`] reported by `device` for the surface held by `holder`
+///
+/// The info is cached until [`refresh`] is called
+///
+/// # Safety
+///
+/// `device` must support the surface,
+/// check [`vku::PhysicalDevRef::supports_surface`](super::PhysicalDevRef::supports_surface)
+pub unsafe fn info<S: super::SurfaceHolder>(
+    holder: &S,
+    device: vk::PhysicalDevice,
+) -> super::Result<SurfaceInfo> {
+    holder
+        .vk_surface_cache()
+        .get(holder.vk_surface_backend(), *holder.vk_surface(), device)
+}
+
+/// Invalidates the cached info of the surface held by `holder`
+///
+/// Must be called when the window is resized or rotated, since the capabilities change.
+/// [`rebind`] and [`vku::Swapchain::recreate_checked`](super::Swapchain::recreate_checked)
+/// call it
+pub fn refresh<S: super::SurfaceHolder>(holder: &S) {
+    holder.vk_surface_cache().clear()
 }
 
 /// A wrapper around all the necessary state needed to hold a Vulkan surface
@@ -19,9 +187,11 @@ pub struct Surface<'a, I: super::InstanceHolder> {
     surface: vk::SurfaceKHR,
     /// A set of function pointers to Vulkan functions related to the KHR extension
     fns: khr::Surface,
+    /// The info reported by the physical devices for this surface
+    cache: SurfaceInfoCache,
 
     /// A marker to the window bound to this surface, the compiler uses this declaration
-    /// - more specifically the lifetime bound to it -
+    /// (more specifically the lifetime bound to it)
     /// to stop the actual window object from being dropped before this value
     /// without requiring any space to store the actual ref
     window: PhantomData<&'a dyn rwh::HasRawWindowHandle>,
@@ -37,31 +207,167 @@ impl<'a, I: super::InstanceHolder> Surface<'a, I> {
         display: rwh::RawDisplayHandle,
         window: rwh::RawWindowHandle,
     ) -> super::Result<Self> {
+        trace_span!("create_surface");
         let surface = unsafe {
             ash_window::create_surface(
                 instance.vk_entry(),
                 instance.vk_instance(),
                 display,
                 window,
-                None,
+                instance.vk_allocator(),
             )
-        }?;
+        }
+        .context_of(Operation::CreateSurface, instance.vk_instance().handle())?;
         Ok(Self {
             surface,
             fns: khr::Surface::new(instance.vk_entry(), instance.vk_instance()),
+            cache: SurfaceInfoCache::default(),
             window: PhantomData,
             instance,
         })
     }
+
+    /// Creates a new Vulkan surface for a window implementing the `raw-window-handle` 0.6 traits
+    ///
+    /// The surface borrows the window, so that it can't be dropped before the surface.
+    /// The instance extensions are returned by [`vku::window_handle::extensions_checked`].
+    #[cfg(feature = "rwh_06")]
+    pub fn from_window<W>(instance: I, window: &'a W) -> super::Result<Self>
+    where
+        W: rwh_06::HasDisplayHandle + rwh_06::HasWindowHandle + ?Sized,
+    {
+        super::window_handle::create_surface(instance, window)
+    }
+
+    /// Creates a new Vulkan surface for a window implementing the `raw-window-handle` 0.5 traits
+    ///
+    /// The surface borrows the window, so that it can't be dropped before the surface.
+    /// The instance extensions are returned by [`vku::window_handle::extensions_checked_rwh05`].
+    #[cfg(feature = "rwh_05")]
+    pub fn from_window_rwh05<W>(instance: I, window: &'a W) -> super::Result<Self>
+    where
+        W: rwh::HasRawDisplayHandle + rwh::HasRawWindowHandle + ?Sized,
+    {
+        super::window_handle::create_surface_rwh05(instance, window)
+    }
+
+    /// Takes the ownership of a surface handle, destroying it when dropped
+    ///
+    /// # Safety
+    ///
+    /// `surface` must be a valid surface created through `instance`,
+    /// must not be destroyed by anyone else and the window it has been created for
+    /// must live for `'a`
+    pub unsafe fn from_raw(instance: I, surface: vk::SurfaceKHR) -> Self {
+        debug_assert_ne!(surface, vk::SurfaceKHR::null());
+        Self {
+            surface,
+            fns: khr::Surface::new(instance.vk_entry(), instance.vk_instance()),
+            cache: SurfaceInfoCache::default(),
+            window: PhantomData,
+            instance,
+        }
+    }
+
+    /// Gives up the ownership of the surface, returning its handle along with
+    /// the instance holder it has been created from
+    ///
+    /// The surface is not destroyed, it's up to the caller to destroy it
+    /// or to adopt it again with [`from_raw`](Self::from_raw)
+    pub fn into_raw(self) -> (vk::SurfaceKHR, I) {
+        let this = std::mem::ManuallyDrop::new(self);
+        unsafe {
+            // SAFETY: `this` is never used nor dropped again,
+            // the fields that own memory are moved out exactly once
+            drop(std::ptr::read(&this.cache));
+            (this.surface, std::ptr::read(&this.instance))
+        }
+    }
+}
+
+/// Destroys the Vulkan surface held by `holder` and creates a new one for the same window
+///
+/// On Android the native window is destroyed when the application is paused and a new one
+/// is provided when it's resumed, this allows to bind the surface to the new native window
+/// while keeping the instance and the logical device alive.
+///
+/// # Safety
+///
+/// There must be no swapchain created for the current surface,
+/// use [`vku::Swapchain::into_inner`](super::Swapchain::into_inner) to destroy it
+/// and recreate it after the surface has been rebound.
+///
+/// `window` must be the raw handle of the window the surface has been created for,
+/// which must outlive the surface
+pub unsafe fn rebind<S: super::SurfaceHolder>(
+    holder: &mut S,
+    display: rwh::RawDisplayHandle,
+    window: rwh::RawWindowHandle,
+) -> super::Result<()> {
+    trace_span!("rebind_surface");
+    holder
+        .vk_surface_fns()
+        .destroy_surface(*holder.vk_surface(), holder.vk_allocator());
+    // Don't leave a dangling handle around if the creation fails
+    *holder.vk_surface_mut() = vk::SurfaceKHR::null();
+
+    let surface = ash_window::create_surface(
+        holder.vk_entry(),
+        holder.vk_instance(),
+        display,
+        window,
+        holder.vk_allocator(),
+    )
+    .context_of(Operation::CreateSurface, holder.vk_instance().handle())?;
+    *holder.vk_surface_mut() = surface;
+    refresh(holder);
+    Ok(())
+}
+
+impl<I: super::InstanceHolder> super::RawHandle for Surface<'_, I> {
+    type Handle = vk::SurfaceKHR;
+
+    fn raw(&self) -> vk::SurfaceKHR {
+        self.surface
+    }
 }
 
 impl<I: super::InstanceHolder> Drop for Surface<'_, I> {
     fn drop(&mut self) {
-        unsafe { self.fns.destroy_surface(self.surface, None) };
+        let allocator = self.instance.vk_allocator();
+        unsafe { self.fns.destroy_surface(self.surface, allocator) };
     }
 }
 
-derive_instance_holder!(Surface<'_, I> = instance: I);
+// Can't use derive_instance_holder! since the surface must be reported
+// as the presentation surface instead of forwarding it
+impl<I: super::InstanceHolder> super::instance::pvt::InstanceHolder for Surface<'_, I> {
+    fn vk_instance(&self) -> &ash::Instance {
+        self.instance.vk_instance()
+    }
+
+    fn vk_entry(&self) -> &ash::Entry {
+        self.instance.vk_entry()
+    }
+
+    fn vk_allocator(&self) -> Option<&vk::AllocationCallbacks> {
+        self.instance.vk_allocator()
+    }
+
+    fn vk_info(&self) -> &super::InstanceInfo {
+        self.instance.vk_info()
+    }
+
+    fn vk_backend(&self) -> &dyn super::backend::Backend {
+        self.instance.vk_backend()
+    }
+
+    fn vk_presentation_surface(
+        &self,
+    ) -> Option<(&dyn super::backend::SurfaceBackend, vk::SurfaceKHR)> {
+        Some((&self.fns, self.surface))
+    }
+}
 
 /// Private definitions available only to the [vku](super) module
 pub(super) mod pvt {
@@ -75,8 +381,19 @@ pub(super) mod pvt {
         /// Returns a reference to the underlying [`khr::Surface`]
         fn vk_surface_fns(&self) -> &khr::Surface;
 
+        /// Returns the backend through which the surface is queried
+        fn vk_surface_backend(&self) -> &dyn crate::backend::SurfaceBackend {
+            self.vk_surface_fns()
+        }
+
         /// Returns a reference to the underlying [`vk::SurfaceKHR`]
         fn vk_surface(&self) -> &vk::SurfaceKHR;
+
+        /// Returns a mutable reference to the underlying [`vk::SurfaceKHR`]
+        fn vk_surface_mut(&mut self) -> &mut vk::SurfaceKHR;
+
+        /// Returns the cache of the info reported by the devices for the surface
+        fn vk_surface_cache(&self) -> &SurfaceInfoCache;
     }
 }
 /// A [`vku::SurfaceHolder`](SurfaceHolder) is a type
@@ -96,6 +413,14 @@ impl<I: super::InstanceHolder> pvt::SurfaceHolder for Surface<'_, I> {
     fn vk_surface(&self) -> &vk::SurfaceKHR {
         &self.surface
     }
+
+    fn vk_surface_mut(&mut self) -> &mut vk::SurfaceKHR {
+        &mut self.surface
+    }
+
+    fn vk_surface_cache(&self) -> &SurfaceInfoCache {
+        &self.cache
+    }
 }
 
 /// Implements the [`SurfaceHolder`] in a transitive way by defining the methods
@@ -107,14 +432,14 @@ impl<I: super::InstanceHolder> pvt::SurfaceHolder for Surface<'_, I> {
 /// # Example
 ///
 /// Derive the trait on a wrapper type
-/// ```
+/// ```text
 /// struct SurfaceWrapper<I: SurfaceHolder>(I);
 ///
 /// derive_surface_holder!(SurfaceWrapper<I> = 0: I);
 /// ```
 ///
 /// Derive the trait on a wrapper type that has additional generics
-/// ```
+/// ```text
 /// struct SurfaceRefWrapper<'a, I: SurfaceHolder>(&'a I);
 ///
 /// derive_surface_holder!(
@@ -134,9 +459,21 @@ macro_rules! derive_surface_holder {
                 self.$field.vk_surface_fns()
             }
 
+            fn vk_surface_backend(&self) -> &dyn $crate::backend::SurfaceBackend {
+                self.$field.vk_surface_backend()
+            }
+
             fn vk_surface(&self) -> &ash::vk::SurfaceKHR {
                 self.$field.vk_surface()
             }
+
+            fn vk_surface_mut(&mut self) -> &mut ash::vk::SurfaceKHR {
+                self.$field.vk_surface_mut()
+            }
+
+            fn vk_surface_cache(&self) -> &$crate::surface::SurfaceInfoCache {
+                self.$field.vk_surface_cache()
+            }
         }
     };
 }
@@ -1,19 +1,45 @@
 #[allow(unused_imports)]
 use crate as vku; // <--- Used in docs
 
-use ash::{extensions::khr, vk};
+#[cfg(feature = "surface")]
+use std::time::Duration;
+
+use ash::vk;
+#[cfg(feature = "surface")]
+use ash::{extensions::khr, prelude::VkResult};
+
+#[cfg(feature = "surface")]
+use super::{ErrorContext, Operation};
 
 /// How the image is to be shared between all the queue families
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum ImageSharing {
     /// The image is owned by one queue family at a time, changing the ownership
     /// must be done explicitly
+    #[default]
     Exclusive,
     /// The image is shared between the queue families contained in this value
     Concurrent(Vec<u32>),
 }
 
 impl ImageSharing {
+    /// Returns the sharing mode for images used by the queue families in `families`
+    ///
+    /// Duplicated indices are removed: when a single family remains the sharing is
+    /// [`Exclusive`](Self::Exclusive), otherwise it's [`Concurrent`](Self::Concurrent)
+    /// between the remaining families
+    pub fn for_families(families: &[u32]) -> Self {
+        let mut unique = families.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+        match unique.len() {
+            0 | 1 => Self::Exclusive,
+            _ => Self::Concurrent(unique),
+        }
+    }
+
     /// Convert the enum into the values expected by the Vulkan API
+    #[cfg(feature = "surface")]
     fn vk_convert(&self) -> (vk::SharingMode, &[u32]) {
         match self {
             ImageSharing::Exclusive => (vk::SharingMode::EXCLUSIVE, &[]),
@@ -23,6 +49,9 @@ impl ImageSharing {
 }
 
 /// Swapchain image details
+///
+/// Use [`ImageDetails::builder`] to get a value consistent with the surface capabilities
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ImageDetails {
     /// Number of buffered images
     pub count: u32,
@@ -34,15 +63,421 @@ pub struct ImageDetails {
     pub extent: vk::Extent2D,
     /// How the images will be shared between the different queue families
     pub sharing: ImageSharing,
-    /// TODO
+    /// The transform applied to the images by the presentation engine before presenting them,
+    /// relative to the natural orientation of the display
+    ///
+    /// Using the `current_transform` of the surface capabilities avoids an additional
+    /// pass of the compositor, but the application must render the images already rotated
     pub transform: vk::SurfaceTransformFlagsKHR,
-    /// TODO
+    /// How the images are queued for presentation and synchronized with the display refresh
     pub present_mode: vk::PresentModeKHR,
 }
 
+/// The reason why an [`ImageDetails`] value is not valid for a surface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidDetails {
+    /// The number of images is outside the range supported by the surface
+    ImageCount { count: u32, min: u32, max: u32 },
+    /// The format and color space pair is not supported by the surface
+    Format(vk::SurfaceFormatKHR),
+    /// The extent is zero or outside the range supported by the surface
+    Extent(vk::Extent2D),
+    /// The concurrent sharing mode has less than two families, duplicates or invalid indices
+    Sharing,
+    /// The transform is not a single bit supported by the surface
+    Transform(vk::SurfaceTransformFlagsKHR),
+    /// The present mode is not supported by the surface
+    PresentMode(vk::PresentModeKHR),
+    /// The device can't present to the surface
+    UnsupportedSurface,
+}
+
+impl std::fmt::Display for InvalidDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ImageCount { count, min, max } => {
+                write!(f, "image count {count} outside of {min}..={max}")
+            }
+            Self::Format(fmt) => write!(
+                f,
+                "unsupported format {:?} {:?}",
+                fmt.format, fmt.color_space
+            ),
+            Self::Extent(ext) => write!(f, "invalid extent {}x{}", ext.width, ext.height),
+            Self::Sharing => f.write_str("invalid concurrent queue families"),
+            Self::Transform(t) => write!(f, "unsupported transform {t:?}"),
+            Self::PresentMode(mode) => write!(f, "unsupported present mode {mode:?}"),
+            Self::UnsupportedSurface => f.write_str("the device can't present to the surface"),
+        }
+    }
+}
+
+impl ImageDetails {
+    /// Creates a builder with the parameters that have no sensible default
+    pub fn builder(
+        format: vk::SurfaceFormatKHR,
+        extent: vk::Extent2D,
+        present_mode: vk::PresentModeKHR,
+    ) -> ImageDetailsBuilder {
+        ImageDetailsBuilder {
+            format,
+            extent,
+            present_mode,
+            count: None,
+            transform: None,
+            sharing: ImageSharing::Exclusive,
+        }
+    }
+
+    /// Creates a builder that follows the user preferences about the swapchain
+    ///
+    /// When [`SwapchainPreferences::image_count`](super::SwapchainPreferences::image_count)
+    /// is set it's used as [`ImageCountPolicy::Exact`], otherwise the
+    /// [policy for the present mode](ImageCountPolicy::for_present_mode) is used
+    pub fn builder_from(
+        prefs: &super::SwapchainPreferences,
+        format: vk::SurfaceFormatKHR,
+        extent: vk::Extent2D,
+        present_mode: vk::PresentModeKHR,
+    ) -> ImageDetailsBuilder {
+        let builder = Self::builder(format, extent, present_mode);
+        match prefs.image_count {
+            Some(count) => builder.count(count),
+            None => builder,
+        }
+    }
+
+    /// Negotiates the details of a swapchain for a window of size `window_extent`,
+    /// from what the device reports about the surface and the user preferences
+    ///
+    /// - the format and the present mode are chosen with
+    ///   [`SwapchainPreferences`](super::SwapchainPreferences)
+    /// - the extent is the one returned by [`extent_for_window`]
+    /// - the image count follows [`builder_from`](Self::builder_from)
+    /// - the sharing is derived from the indices of the queue families that use the images,
+    ///   usually the graphics and the present ones, check [`ImageSharing::for_families`]
+    ///
+    /// Returns [`None`] if the surface reports no format or no present mode
+    #[cfg(feature = "surface")]
+    pub fn negotiate(
+        prefs: &super::SwapchainPreferences,
+        info: &super::surface::SurfaceInfo,
+        window_extent: vk::Extent2D,
+        queue_families: &[u32],
+    ) -> Option<Self> {
+        let format = prefs.choose_format(&info.formats)?;
+        if info.present_modes.is_empty() {
+            return None;
+        }
+        let present_mode = prefs.choose_present_mode(&info.present_modes);
+        let extent = extent_for_window(&info.capabilities, window_extent);
+        let details = Self::builder_from(prefs, format, extent, present_mode)
+            .queue_families(queue_families)
+            .build(&info.capabilities);
+        Some(details)
+    }
+
+    /// Checks that these details can be used to create a swapchain for a surface with
+    /// the provided capabilities, formats and present modes
+    ///
+    /// `queue_family_count` is the number of queue families of the device,
+    /// used to check the indices of [`ImageSharing::Concurrent`].
+    ///
+    /// This covers the requirements listed in the safety section of [`Swapchain::new`],
+    /// apart from the surface being supported by the device.
+    pub fn validate(
+        &self,
+        caps: &vk::SurfaceCapabilitiesKHR,
+        formats: &[vk::SurfaceFormatKHR],
+        modes: &[vk::PresentModeKHR],
+        queue_family_count: u32,
+    ) -> Result<(), InvalidDetails> {
+        let shared = [
+            vk::PresentModeKHR::SHARED_DEMAND_REFRESH,
+            vk::PresentModeKHR::SHARED_CONTINUOUS_REFRESH,
+        ];
+        let (min, max) = match shared.contains(&self.present_mode) {
+            true => (1, 1),
+            false => (
+                caps.min_image_count,
+                match caps.max_image_count {
+                    0 => u32::MAX,
+                    max => max,
+                },
+            ),
+        };
+        if !(min..=max).contains(&self.count) {
+            return Err(InvalidDetails::ImageCount {
+                count: self.count,
+                min,
+                max,
+            });
+        }
+
+        let format = vk::SurfaceFormatKHR {
+            format: self.format,
+            color_space: self.color_space,
+        };
+        if !formats.contains(&format) {
+            return Err(InvalidDetails::Format(format));
+        }
+
+        let (ext, lo, hi) = (self.extent, caps.min_image_extent, caps.max_image_extent);
+        if ext.width == 0
+            || ext.height == 0
+            || !(lo.width..=hi.width).contains(&ext.width)
+            || !(lo.height..=hi.height).contains(&ext.height)
+        {
+            return Err(InvalidDetails::Extent(ext));
+        }
+
+        if let ImageSharing::Concurrent(families) = &self.sharing {
+            let mut unique = families.clone();
+            unique.sort_unstable();
+            unique.dedup();
+            if families.len() < 2
+                || unique.len() != families.len()
+                || families.iter().any(|&i| i >= queue_family_count)
+            {
+                return Err(InvalidDetails::Sharing);
+            }
+        }
+
+        if self.transform.as_raw().count_ones() != 1
+            || !caps.supported_transforms.contains(self.transform)
+        {
+            return Err(InvalidDetails::Transform(self.transform));
+        }
+
+        if !modes.contains(&self.present_mode) {
+            return Err(InvalidDetails::PresentMode(self.present_mode));
+        }
+        Ok(())
+    }
+}
+
+/// How many images the swapchain should have
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageCountPolicy {
+    /// The minimum number of images supported by the surface
+    Minimum,
+    /// One image more than the minimum, so that the application doesn't have
+    /// to wait for the presentation engine to release an image
+    MinimumPlusOne,
+    /// Exactly this number of images
+    Exact(u32),
+    /// Three images, one being displayed, one queued and one being rendered
+    TripleBuffered,
+}
+
+impl ImageCountPolicy {
+    /// Returns the default policy for the present mode
+    ///
+    /// [`MAILBOX`](vk::PresentModeKHR::MAILBOX) needs an image to replace the queued one
+    /// while another is displayed, so it's [`TripleBuffered`](Self::TripleBuffered),
+    /// the other modes use [`MinimumPlusOne`](Self::MinimumPlusOne)
+    pub fn for_present_mode(present_mode: vk::PresentModeKHR) -> Self {
+        match present_mode {
+            vk::PresentModeKHR::MAILBOX => Self::TripleBuffered,
+            _ => Self::MinimumPlusOne,
+        }
+    }
+
+    /// Returns the effective number of images for a surface with the provided capabilities
+    ///
+    /// The count is clamped with [`clamp_image_count`]
+    pub fn resolve(&self, caps: &vk::SurfaceCapabilitiesKHR) -> u32 {
+        let desired = match *self {
+            Self::Minimum => caps.min_image_count,
+            Self::MinimumPlusOne => caps.min_image_count.saturating_add(1),
+            Self::Exact(count) => count,
+            Self::TripleBuffered => 3,
+        };
+        clamp_image_count(caps, desired)
+    }
+}
+
+/// Clamps the `desired` number of images between the minimum and the maximum
+/// supported by a surface with the provided capabilities
+///
+/// A `max_image_count` of `0` means there is no maximum. The maximum takes precedence,
+/// so if a driver reports a minimum greater than the maximum the latter is returned
+pub fn clamp_image_count(caps: &vk::SurfaceCapabilitiesKHR, desired: u32) -> u32 {
+    let count = desired.max(caps.min_image_count);
+    match caps.max_image_count {
+        0 => count,
+        max => count.min(max),
+    }
+}
+
+/// Returns the extent of the swapchain images for a window of size `window_extent`
+///
+/// That's the `current_extent` of the surface, unless it's the special value `0xFFFFFFFF`
+/// meaning that the size is determined by the swapchain, in which case the window size
+/// clamped to the extents supported by the surface is returned
+pub fn extent_for_window(
+    caps: &vk::SurfaceCapabilitiesKHR,
+    window_extent: vk::Extent2D,
+) -> vk::Extent2D {
+    let (min, max) = (caps.min_image_extent, caps.max_image_extent);
+    match caps.current_extent.width {
+        u32::MAX => vk::Extent2D {
+            width: window_extent.width.clamp(min.width, max.width),
+            height: window_extent.height.clamp(min.height, max.height),
+        },
+        _ => caps.current_extent,
+    }
+}
+
+/// Builder of [`ImageDetails`], returned by [`ImageDetails::builder`]
+#[derive(Clone, Debug)]
+pub struct ImageDetailsBuilder {
+    format: vk::SurfaceFormatKHR,
+    extent: vk::Extent2D,
+    present_mode: vk::PresentModeKHR,
+    count: Option<ImageCountPolicy>,
+    transform: Option<vk::SurfaceTransformFlagsKHR>,
+    sharing: ImageSharing,
+}
+
+impl ImageDetailsBuilder {
+    /// Sets the number of images, same as [`ImageCountPolicy::Exact`]
+    pub fn count(self, count: u32) -> Self {
+        self.image_count(ImageCountPolicy::Exact(count))
+    }
+
+    /// Sets how many images to use, by default it's the
+    /// [policy for the present mode](ImageCountPolicy::for_present_mode)
+    pub fn image_count(self, policy: ImageCountPolicy) -> Self {
+        Self {
+            count: Some(policy),
+            ..self
+        }
+    }
+
+    /// Sets the transform, by default it's the current transform of the surface
+    pub fn transform(self, transform: vk::SurfaceTransformFlagsKHR) -> Self {
+        Self {
+            transform: Some(transform),
+            ..self
+        }
+    }
+
+    /// Sets how the images are shared, by default it's [`ImageSharing::Exclusive`]
+    pub fn sharing(self, sharing: ImageSharing) -> Self {
+        Self { sharing, ..self }
+    }
+
+    /// Sets the sharing for the images used by the queue families in `families`,
+    /// e.g. the graphics and the present ones
+    ///
+    /// The images are [`Exclusive`](ImageSharing::Exclusive) when there is a single family,
+    /// check [`ImageSharing::for_families`]
+    pub fn queue_families(self, families: &[u32]) -> Self {
+        self.sharing(ImageSharing::for_families(families))
+    }
+
+    /// Builds the details filling the missing parameters from the capabilities of the surface
+    pub fn build(self, caps: &vk::SurfaceCapabilitiesKHR) -> ImageDetails {
+        let policy = self
+            .count
+            .unwrap_or_else(|| ImageCountPolicy::for_present_mode(self.present_mode));
+        let count = policy.resolve(caps);
+        ImageDetails {
+            count,
+            format: self.format.format,
+            color_space: self.format.color_space,
+            extent: self.extent,
+            sharing: self.sharing,
+            transform: self.transform.unwrap_or(caps.current_transform),
+            present_mode: self.present_mode,
+        }
+    }
+}
+
+/// Returns the 2D matrix that rotates (and mirrors) clip space coordinates
+/// like the presentation engine will do with an image presented with `transform`
+///
+/// The matrix is row-major and multiplies column vectors. A renderer that uses the
+/// `current_transform` as pre-transform must apply it after the projection,
+/// so that the image is shown with the right orientation.
+///
+/// [`INHERIT`](vk::SurfaceTransformFlagsKHR::INHERIT) and unknown values map to the identity
+pub fn transform_to_matrix(transform: vk::SurfaceTransformFlagsKHR) -> [[f32; 2]; 2] {
+    use vk::SurfaceTransformFlagsKHR as T;
+    let (rotation, mirror) = match transform {
+        T::ROTATE_90 => (1, false),
+        T::ROTATE_180 => (2, false),
+        T::ROTATE_270 => (3, false),
+        T::HORIZONTAL_MIRROR => (0, true),
+        T::HORIZONTAL_MIRROR_ROTATE_90 => (1, true),
+        T::HORIZONTAL_MIRROR_ROTATE_180 => (2, true),
+        T::HORIZONTAL_MIRROR_ROTATE_270 => (3, true),
+        _ => (0, false),
+    };
+    // Exact values of the cosine and sine of the multiples of 90 degrees
+    let (cos, sin) = [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)][rotation];
+    let flip = if mirror { -1.0 } else { 1.0 };
+    [[cos * flip, -sin], [sin * flip, cos]]
+}
+
+/// Returns `extent` with width and height swapped if `transform` rotates by 90 or 270 degrees
+///
+/// This converts between the size of the window, as seen by the application,
+/// and the size of the swapchain images when they are pre-transformed
+pub fn rotated_extent(
+    extent: vk::Extent2D,
+    transform: vk::SurfaceTransformFlagsKHR,
+) -> vk::Extent2D {
+    use vk::SurfaceTransformFlagsKHR as T;
+    let quarter = T::ROTATE_90
+        | T::ROTATE_270
+        | T::HORIZONTAL_MIRROR_ROTATE_90
+        | T::HORIZONTAL_MIRROR_ROTATE_270;
+    match quarter.intersects(transform) {
+        true => vk::Extent2D {
+            width: extent.height,
+            height: extent.width,
+        },
+        false => extent,
+    }
+}
+
+/// The outcome of [`Swapchain::acquire_next_image`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcquireOutcome {
+    /// The image at `index` has been acquired
+    Acquired {
+        /// Index of the image in the [`Vec`] returned by [`Swapchain::images`]
+        index: u32,
+        /// The swapchain no longer matches the surface properties exactly,
+        /// but can still be used to present
+        suboptimal: bool,
+    },
+    /// No image was available before the timeout expired
+    NotReady,
+}
+
+impl AcquireOutcome {
+    /// Maps the value returned by [`acquire_next_image`](ash::extensions::khr::Swapchain::acquire_next_image)
+    /// treating `VK_TIMEOUT` and `VK_NOT_READY` as non-errors
+    #[cfg(feature = "surface")]
+    fn from_vk(result: VkResult<(u32, bool)>) -> VkResult<Self> {
+        match result {
+            Ok((index, suboptimal)) => Ok(Self::Acquired { index, suboptimal }),
+            Err(vk::Result::TIMEOUT | vk::Result::NOT_READY) => Ok(Self::NotReady),
+            Err(err) => Err(err),
+        }
+    }
+}
+
 /// A wrapper around all the necessary state needed to hold a Vulkan swapchain
 ///
 /// A Vulkan swapchain handles how the rendered images are stored and buffered
+///
+/// The swapchain is destroyed when dropped without waiting for the device,
+/// so the work using its images must have completed by then
+#[cfg(feature = "surface")]
 pub struct Swapchain<I: super::SurfaceHolder + super::DeviceHolder> {
     /// The instance which this swapchain belongs to
     instance: I,
@@ -50,8 +485,11 @@ pub struct Swapchain<I: super::SurfaceHolder + super::DeviceHolder> {
     fns: khr::Swapchain,
     /// The Vulkan swapchain handle
     swapchain: vk::SwapchainKHR,
+    /// The details the swapchain has been created with
+    details: ImageDetails,
 }
 
+#[cfg(feature = "surface")]
 impl<I: super::SurfaceHolder + super::DeviceHolder> Swapchain<I> {
     /// Creates a new Vulkan swapchain
     ///
@@ -96,14 +534,41 @@ impl<I: super::SurfaceHolder + super::DeviceHolder> Swapchain<I> {
     ///   [`vku::PhysicalDevRef::queue_families`] of the selected device
     ///
     /// - `transform` must be one of the bits present in the `supported_transforms` member of the
-    ///    [`vk::SurfaceCapabilitiesKHR`] structure returned by
-    ///    [`vku::PhysicalDevRef::surface_capabilities`] for the surface
+    ///   [`vk::SurfaceCapabilitiesKHR`] structure returned by
+    ///   [`vku::PhysicalDevRef::surface_capabilities`] for the surface
     ///
     /// - `present_mode` must be one of the [`vk::PresentModeKHR`] values returned by
     ///   [`vku::PhysicalDevRef::surface_present_modes`] for the surface
     pub unsafe fn new(instance: I, details: ImageDetails) -> super::Result<Self> {
+        trace_span!(
+            "create_swapchain",
+            count = details.count,
+            format = ?details.format,
+            extent = ?details.extent,
+            present_mode = ?details.present_mode,
+        );
         let fns = khr::Swapchain::new(instance.vk_instance(), instance.vk_device());
+        let swapchain = Self::create(&instance, &fns, &details, vk::SwapchainKHR::null())?;
+        Ok(Self {
+            instance,
+            fns,
+            swapchain,
+            details,
+        })
+    }
 
+    /// Creates the swapchain handle, retiring `old_swapchain` if it's not null
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new), `old_swapchain` must be either null or a non retired
+    /// swapchain created for the same surface
+    unsafe fn create(
+        instance: &I,
+        fns: &khr::Swapchain,
+        details: &ImageDetails,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> super::Result<vk::SwapchainKHR> {
         let (sharing_mode, queue_indices) = details.sharing.vk_convert();
         let create_info = vk::SwapchainCreateInfoKHR::builder()
             .surface(*instance.vk_surface())
@@ -120,24 +585,664 @@ impl<I: super::SurfaceHolder + super::DeviceHolder> Swapchain<I> {
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
             .present_mode(details.present_mode)
             .clipped(true)
+            .old_swapchain(old_swapchain)
             .build();
 
-        let swapchain = fns.create_swapchain(&create_info, None)?;
-        Ok(Self {
-            instance,
-            fns,
+        fns.create_swapchain(&create_info, instance.vk_allocator())
+            .context_of(Operation::CreateSwapchain, instance.vk_device().handle())
+    }
+
+    /// Creates a new Vulkan swapchain after checking that `details` are valid for the surface
+    ///
+    /// The surface properties are queried from the physical device the logical device has been
+    /// created from, then checked with [`ImageDetails::validate`]
+    pub fn new_checked(instance: I, details: ImageDetails) -> super::Result<Self> {
+        Self::check(&instance, &details)?;
+        // SAFETY: The details have just been validated
+        unsafe { Self::new(instance, details) }
+    }
+
+    /// Recreates the swapchain with new `details`, e.g. after the window has been resized
+    ///
+    /// The cached surface info is [refreshed](super::surface::refresh) before checking the
+    /// details like [`new_checked`](Self::new_checked) does. The current swapchain is passed
+    /// as the old one and then destroyed, so the work using its images must have completed.
+    ///
+    /// If the details are not valid the current swapchain is kept as is, while if the driver
+    /// fails to create the new one it's kept but retired, so no more images can be acquired.
+    pub fn recreate_checked(&mut self, details: ImageDetails) -> super::Result<()> {
+        trace_span!(
+            "recreate_swapchain",
+            count = details.count,
+            extent = ?details.extent,
+            present_mode = ?details.present_mode,
+        );
+        super::surface::refresh(&self.instance);
+        Self::check(&self.instance, &details)?;
+        // SAFETY: The details have just been validated and the current swapchain is not retired
+        let swapchain =
+            unsafe { Self::create(&self.instance, &self.fns, &details, self.swapchain)? };
+        unsafe { self.destroy() };
+        self.swapchain = swapchain;
+        self.details = details;
+        Ok(())
+    }
+
+    /// Checks that `details` are valid for the surface and the device held by `instance`
+    fn check(instance: &I, details: &ImageDetails) -> super::Result<()> {
+        let phydev = instance.vk_device_info().physical_device;
+        let (fns, surface) = (instance.vk_surface_backend(), *instance.vk_surface());
+        let families = unsafe {
+            instance
+                .vk_backend()
+                .get_physical_device_queue_family_properties(phydev)
+        };
+        unsafe {
+            let mut supported = false;
+            for family in 0..families.len() as u32 {
+                supported |= fns
+                    .get_physical_device_surface_support(phydev, family, surface)
+                    .context_of(Operation::GetSurfaceSupport, phydev)?;
+            }
+            if !supported {
+                return Err(InvalidDetails::UnsupportedSurface.into());
+            }
+            let info = super::surface::info(instance, phydev)?;
+            details.validate(
+                &info.capabilities,
+                &info.formats,
+                &info.present_modes,
+                families.len() as u32,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Destroys the swapchain and returns the holder it has been created from
+    ///
+    /// Useful when the surface must be rebound, check [`vku::surface::rebind`]
+    pub fn into_inner(self) -> I {
+        let this = std::mem::ManuallyDrop::new(self);
+        unsafe {
+            this.destroy();
+            // SAFETY: `this` is never used nor dropped again,
+            // the fields that own memory are moved out exactly once
+            drop(std::ptr::read(&this.details));
+            std::ptr::read(&this.instance)
+        }
+    }
+
+    /// Gives up the ownership of the swapchain, returning its handle along with
+    /// the holder it has been created from
+    ///
+    /// The swapchain is not destroyed, it's up to the caller to destroy it
+    /// or to adopt it again with [`from_raw`](Self::from_raw)
+    pub fn into_raw(self) -> (vk::SwapchainKHR, I) {
+        let this = std::mem::ManuallyDrop::new(self);
+        unsafe {
+            // SAFETY: `this` is never used nor dropped again,
+            // the fields that own memory are moved out exactly once
+            drop(std::ptr::read(&this.details));
+            (this.swapchain, std::ptr::read(&this.instance))
+        }
+    }
+
+    /// Takes the ownership of a swapchain handle, destroying it when dropped
+    ///
+    /// # Safety
+    ///
+    /// `swapchain` must be a valid swapchain created through the device and for the surface
+    /// held by `instance` with the parameters in `details`,
+    /// and must not be destroyed by anyone else
+    pub unsafe fn from_raw(
+        instance: I,
+        swapchain: vk::SwapchainKHR,
+        details: ImageDetails,
+    ) -> Self {
+        debug_assert_ne!(swapchain, vk::SwapchainKHR::null());
+        Self {
+            fns: khr::Swapchain::new(instance.vk_instance(), instance.vk_device()),
             swapchain,
-        })
+            details,
+            instance,
+        }
+    }
+
+    /// Destroys the Vulkan swapchain
+    ///
+    /// # Safety
+    ///
+    /// Must be called only once
+    unsafe fn destroy(&self) {
+        let allocator = self.instance.vk_allocator();
+        self.fns.destroy_swapchain(self.swapchain, allocator)
+    }
+
+    /// Returns the details the swapchain has been created with
+    pub fn details(&self) -> &ImageDetails {
+        &self.details
+    }
+
+    /// Returns the transform applied by the presentation engine to the images,
+    /// which the renderer must compensate for, e.g. using [`transform_to_matrix`]
+    ///
+    /// The transform of the surface changes when the device rotates, so when the
+    /// swapchain is recreated the `current_transform` must be queried again
+    pub fn pre_transform(&self) -> vk::SurfaceTransformFlagsKHR {
+        self.details.transform
     }
 
     /// Gets the swapchain images
     pub fn images(&self) -> super::Result<Vec<vk::Image>> {
         unsafe { self.fns.get_swapchain_images(self.swapchain) }
+            .context_of(Operation::GetSwapchainImages, self.swapchain)
+    }
+
+    /// Acquires the next available image of the swapchain, waiting at most `timeout`
+    ///
+    /// The timeout is converted to nanoseconds, saturating at [`u64::MAX`] which
+    /// Vulkan treats as an infinite wait.
+    ///
+    /// `semaphore` and `fence` are signaled when the presentation engine
+    /// stops using the image, either of them can be null but not both.
+    ///
+    /// # Safety
+    ///
+    /// `semaphore` and `fence`, when not null, must be unsignaled
+    /// and must not have any pending operation
+    pub unsafe fn acquire_next_image(
+        &self,
+        timeout: Duration,
+        semaphore: vk::Semaphore,
+        fence: vk::Fence,
+    ) -> super::Result<AcquireOutcome> {
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        let result = self
+            .fns
+            .acquire_next_image(self.swapchain, timeout, semaphore, fence);
+        AcquireOutcome::from_vk(result).context_of(Operation::AcquireNextImage, self.swapchain)
+    }
+
+    /// Queues the image at `index` for presentation on `queue`
+    /// once all the `wait_semaphores` are signaled
+    ///
+    /// Returns whether the swapchain is suboptimal,
+    /// in which case it should be recreated but can still be used
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `queue` can't present to the surface
+    ///
+    /// # Safety
+    ///
+    /// `index` must refer to an image acquired with [`acquire_next_image`](Self::acquire_next_image)
+    /// and not yet presented, and `queue` must belong to the device of the swapchain
+    /// and must not be used from other threads meanwhile
+    pub unsafe fn present(
+        &self,
+        queue: &super::Queue,
+        index: u32,
+        wait_semaphores: &[vk::Semaphore],
+    ) -> super::Result<bool> {
+        debug_assert!(
+            queue.supports_present,
+            "queue family {} can't present to the surface",
+            queue.family_index
+        );
+        let swapchains = [self.swapchain];
+        let indices = [index];
+        let present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&indices);
+        self.fns
+            .queue_present(queue.handle, &present_info)
+            .context_of(Operation::QueuePresent, self.swapchain)
     }
 }
 
+#[cfg(feature = "surface")]
+impl<I: super::SurfaceHolder + super::DeviceHolder> super::RawHandle for Swapchain<I> {
+    type Handle = vk::SwapchainKHR;
+
+    fn raw(&self) -> vk::SwapchainKHR {
+        self.swapchain
+    }
+}
+
+#[cfg(feature = "surface")]
 impl<I: super::SurfaceHolder + super::DeviceHolder> Drop for Swapchain<I> {
     fn drop(&mut self) {
-        unsafe { self.fns.destroy_swapchain(self.swapchain, None) }
+        unsafe { self.destroy() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(min_image_count: u32, max_image_count: u32) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            min_image_count,
+            max_image_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn image_count_without_maximum() {
+        assert_eq!(clamp_image_count(&caps(2, 0), 1), 2);
+        assert_eq!(clamp_image_count(&caps(2, 0), 3), 3);
+        assert_eq!(clamp_image_count(&caps(2, 0), u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn image_count_below_minimum() {
+        assert_eq!(clamp_image_count(&caps(3, 8), 0), 3);
+        assert_eq!(clamp_image_count(&caps(3, 8), 2), 3);
+    }
+
+    #[test]
+    fn image_count_above_maximum() {
+        assert_eq!(clamp_image_count(&caps(2, 3), 4), 3);
+        assert_eq!(clamp_image_count(&caps(2, 3), u32::MAX), 3);
+    }
+
+    #[test]
+    fn image_count_in_range() {
+        for desired in 2..=4 {
+            assert_eq!(clamp_image_count(&caps(2, 4), desired), desired);
+        }
+    }
+
+    #[test]
+    fn image_count_with_single_value() {
+        for desired in [0, 1, 2, 3] {
+            assert_eq!(clamp_image_count(&caps(2, 2), desired), 2);
+        }
+    }
+
+    #[test]
+    fn image_count_maximum_wins_over_minimum() {
+        assert_eq!(clamp_image_count(&caps(4, 3), 5), 3);
+        assert_eq!(clamp_image_count(&caps(4, 3), 1), 3);
+    }
+
+    #[test]
+    fn builder_from_preferences() {
+        let caps = caps(2, 4);
+        let (format, extent) = (vk::SurfaceFormatKHR::default(), vk::Extent2D::default());
+        let details = |prefs: &crate::SwapchainPreferences, mode| {
+            ImageDetails::builder_from(prefs, format, extent, mode).build(&caps)
+        };
+
+        let mut prefs = crate::SwapchainPreferences::default();
+        assert_eq!(details(&prefs, vk::PresentModeKHR::FIFO).count, 3);
+        assert_eq!(details(&prefs, vk::PresentModeKHR::MAILBOX).count, 3);
+
+        prefs.image_count = Some(2);
+        assert_eq!(details(&prefs, vk::PresentModeKHR::MAILBOX).count, 2);
+        // The preference is still clamped to what the surface supports
+        prefs.image_count = Some(8);
+        assert_eq!(details(&prefs, vk::PresentModeKHR::FIFO).count, 4);
+    }
+
+    fn surface_caps(current: vk::Extent2D) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            min_image_count: 2,
+            max_image_count: 8,
+            current_extent: current,
+            min_image_extent: vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            max_image_extent: vk::Extent2D {
+                width: 4096,
+                height: 4096,
+            },
+            supported_transforms: vk::SurfaceTransformFlagsKHR::IDENTITY,
+            current_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+            ..Default::default()
+        }
+    }
+
+    const UNDEFINED_EXTENT: vk::Extent2D = vk::Extent2D {
+        width: u32::MAX,
+        height: u32::MAX,
+    };
+
+    #[test]
+    fn extent_follows_the_surface() {
+        let current = vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        let window = vk::Extent2D {
+            width: 640,
+            height: 480,
+        };
+        assert_eq!(extent_for_window(&surface_caps(current), window), current);
+        assert_eq!(
+            extent_for_window(&surface_caps(UNDEFINED_EXTENT), window),
+            window
+        );
+        let huge = vk::Extent2D {
+            width: 10000,
+            height: 0,
+        };
+        assert_eq!(
+            extent_for_window(&surface_caps(UNDEFINED_EXTENT), huge),
+            vk::Extent2D {
+                width: 4096,
+                height: 1
+            }
+        );
+    }
+
+    #[cfg(feature = "surface")]
+    fn surface_info() -> crate::surface::SurfaceInfo {
+        crate::surface::SurfaceInfo {
+            capabilities: surface_caps(UNDEFINED_EXTENT),
+            formats: vec![vk::SurfaceFormatKHR {
+                format: vk::Format::B8G8R8A8_SRGB,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            }],
+            present_modes: vec![vk::PresentModeKHR::FIFO, vk::PresentModeKHR::MAILBOX],
+        }
+    }
+
+    /// The sharing negotiated for the graphics and present families picked among `families`,
+    /// where only the families in `present` can present
+    #[cfg(feature = "surface")]
+    fn negotiated_sharing(families: &[vk::QueueFlags], present: &[u32]) -> ImageSharing {
+        let families: Vec<_> = families
+            .iter()
+            .map(|&queue_flags| vk::QueueFamilyProperties {
+                queue_flags,
+                queue_count: 1,
+                ..Default::default()
+            })
+            .collect();
+        let (graphics, present) =
+            crate::queue_family::graphics_and_present(&families, |i| present.contains(&i)).unwrap();
+        let prefs = crate::SwapchainPreferences::default();
+        let window = vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        ImageDetails::negotiate(&prefs, &surface_info(), window, &[graphics, present])
+            .unwrap()
+            .sharing
+    }
+
+    #[cfg(feature = "surface")]
+    #[test]
+    fn negotiate_sharing_shared_family() {
+        use vk::QueueFlags as Q;
+        let families = [Q::GRAPHICS | Q::COMPUTE | Q::TRANSFER, Q::TRANSFER];
+        assert_eq!(negotiated_sharing(&families, &[0]), ImageSharing::Exclusive);
+        // A family supporting both is preferred over a split pair
+        let families = [Q::GRAPHICS, Q::COMPUTE, Q::GRAPHICS];
+        assert_eq!(
+            negotiated_sharing(&families, &[1, 2]),
+            ImageSharing::Exclusive
+        );
+    }
+
+    #[cfg(feature = "surface")]
+    #[test]
+    fn negotiate_sharing_split_families() {
+        use vk::QueueFlags as Q;
+        let families = [Q::GRAPHICS | Q::COMPUTE, Q::COMPUTE | Q::TRANSFER];
+        assert_eq!(
+            negotiated_sharing(&families, &[1]),
+            ImageSharing::Concurrent(vec![0, 1])
+        );
+    }
+
+    #[cfg(feature = "surface")]
+    #[test]
+    fn negotiate_sharing_present_only_family() {
+        use vk::QueueFlags as Q;
+        let families = [Q::GRAPHICS | Q::COMPUTE, Q::TRANSFER, Q::empty()];
+        assert_eq!(
+            negotiated_sharing(&families, &[2]),
+            ImageSharing::Concurrent(vec![0, 2])
+        );
+    }
+
+    #[cfg(feature = "surface")]
+    #[test]
+    fn negotiate_details() {
+        let prefs = crate::SwapchainPreferences::default();
+        let window = vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        let details = ImageDetails::negotiate(&prefs, &surface_info(), window, &[0]).unwrap();
+        assert_eq!(
+            details,
+            ImageDetails {
+                count: 3,
+                // The preferred format is not available, the first one is used
+                format: vk::Format::B8G8R8A8_SRGB,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                extent: window,
+                sharing: ImageSharing::Exclusive,
+                transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+                present_mode: vk::PresentModeKHR::MAILBOX,
+            }
+        );
+        let info = surface_info();
+        assert_eq!(
+            details.validate(&info.capabilities, &info.formats, &info.present_modes, 1),
+            Ok(())
+        );
+
+        let mut empty = surface_info();
+        empty.formats.clear();
+        assert_eq!(ImageDetails::negotiate(&prefs, &empty, window, &[0]), None);
+        let mut empty = surface_info();
+        empty.present_modes.clear();
+        assert_eq!(ImageDetails::negotiate(&prefs, &empty, window, &[0]), None);
+    }
+
+    use vk::SurfaceTransformFlagsKHR as T;
+
+    /// Each transform with its matrix and whether it swaps width and height
+    const TRANSFORMS: [(T, [[f32; 2]; 2], bool); 8] = [
+        (T::IDENTITY, [[1.0, 0.0], [0.0, 1.0]], false),
+        (T::ROTATE_90, [[0.0, -1.0], [1.0, 0.0]], true),
+        (T::ROTATE_180, [[-1.0, 0.0], [0.0, -1.0]], false),
+        (T::ROTATE_270, [[0.0, 1.0], [-1.0, 0.0]], true),
+        (T::HORIZONTAL_MIRROR, [[-1.0, 0.0], [0.0, 1.0]], false),
+        (
+            T::HORIZONTAL_MIRROR_ROTATE_90,
+            [[0.0, -1.0], [-1.0, 0.0]],
+            true,
+        ),
+        (
+            T::HORIZONTAL_MIRROR_ROTATE_180,
+            [[1.0, 0.0], [0.0, -1.0]],
+            false,
+        ),
+        (
+            T::HORIZONTAL_MIRROR_ROTATE_270,
+            [[0.0, 1.0], [1.0, 0.0]],
+            true,
+        ),
+    ];
+
+    fn apply(m: [[f32; 2]; 2], [x, y]: [f32; 2]) -> [f32; 2] {
+        [m[0][0] * x + m[0][1] * y, m[1][0] * x + m[1][1] * y]
+    }
+
+    #[test]
+    fn transform_matrices() {
+        for (transform, matrix, _) in TRANSFORMS {
+            assert_eq!(transform_to_matrix(transform), matrix, "{transform:?}");
+        }
+        assert_eq!(transform_to_matrix(T::INHERIT), TRANSFORMS[0].1);
+    }
+
+    #[test]
+    fn transform_rotates_clockwise() {
+        // Clip space has the y axis pointing down, so a clockwise rotation
+        // by 90 degrees moves the right edge to the bottom
+        let right = [1.0, 0.0];
+        assert_eq!(apply(transform_to_matrix(T::ROTATE_90), right), [0.0, 1.0]);
+        assert_eq!(
+            apply(transform_to_matrix(T::ROTATE_180), right),
+            [-1.0, 0.0]
+        );
+        assert_eq!(
+            apply(transform_to_matrix(T::ROTATE_270), right),
+            [0.0, -1.0]
+        );
+        // The image is mirrored first and then rotated
+        let mirrored_90 = transform_to_matrix(T::HORIZONTAL_MIRROR_ROTATE_90);
+        assert_eq!(apply(mirrored_90, right), [0.0, -1.0]);
+    }
+
+    #[test]
+    fn transform_extents() {
+        let extent = vk::Extent2D {
+            width: 1920,
+            height: 1080,
+        };
+        let swapped = vk::Extent2D {
+            width: 1080,
+            height: 1920,
+        };
+        for (transform, _, swaps) in TRANSFORMS {
+            let expected = if swaps { swapped } else { extent };
+            assert_eq!(rotated_extent(extent, transform), expected, "{transform:?}");
+        }
+        assert_eq!(rotated_extent(extent, T::INHERIT), extent);
+    }
+
+    #[test]
+    #[cfg(feature = "surface")]
+    fn acquire_outcome_success() {
+        assert_eq!(
+            AcquireOutcome::from_vk(Ok((2, false))),
+            Ok(AcquireOutcome::Acquired {
+                index: 2,
+                suboptimal: false
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "surface")]
+    fn acquire_outcome_suboptimal() {
+        // ash reports VK_SUBOPTIMAL_KHR as a success with the flag set
+        assert_eq!(
+            AcquireOutcome::from_vk(Ok((0, true))),
+            Ok(AcquireOutcome::Acquired {
+                index: 0,
+                suboptimal: true
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "surface")]
+    fn acquire_outcome_not_ready() {
+        for result in [vk::Result::TIMEOUT, vk::Result::NOT_READY] {
+            assert_eq!(
+                AcquireOutcome::from_vk(Err(result)),
+                Ok(AcquireOutcome::NotReady)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "surface")]
+    fn acquire_outcome_errors() {
+        for result in [
+            vk::Result::ERROR_OUT_OF_DATE_KHR,
+            vk::Result::ERROR_SURFACE_LOST_KHR,
+            vk::Result::ERROR_DEVICE_LOST,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+        ] {
+            assert_eq!(AcquireOutcome::from_vk(Err(result)), Err(result));
+        }
+    }
+
+    #[cfg(feature = "surface")]
+    const MOCK_WINDOW: vk::Extent2D = vk::Extent2D {
+        width: 800,
+        height: 600,
+    };
+
+    /// A device that presents to the surface with the [`surface_caps`],
+    /// only in B8G8R8A8_SRGB with FIFO, if `present` is `true`
+    #[cfg(feature = "surface")]
+    fn mock_device(present: bool) -> crate::backend::mock::Device {
+        let format = vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        let mut device =
+            crate::backend::mock::Device::new("gpu", vk::PhysicalDeviceType::DISCRETE_GPU, 0)
+                .presenting(
+                    surface_caps(MOCK_WINDOW),
+                    vec![format],
+                    vec![vk::PresentModeKHR::FIFO],
+                );
+        device.present_families = vec![present];
+        device
+    }
+
+    /// Creates a swapchain with `details` for a [`mock_device`] through `new_checked`,
+    /// which must fail before the swapchain is created
+    #[cfg(feature = "surface")]
+    fn new_checked_error(present: bool, details: ImageDetails) -> crate::Error {
+        let instance = crate::backend::mock::Instance::new(vec![mock_device(present)]);
+        let list = crate::PhysicalDevList::list(instance.with_surface()).unwrap();
+        let queues = vec![crate::QueueFamilyInfo {
+            index: 0,
+            priorities: vec![1.0],
+        }];
+        let no_names: [&std::ffi::CStr; 0] = [];
+        let dev = unsafe { list.select_checked(0, queues, no_names) }.unwrap();
+        let Err(err) = Swapchain::new_checked(dev, details) else {
+            panic!("the swapchain has been created");
+        };
+        err
+    }
+
+    #[cfg(feature = "surface")]
+    fn mock_details(format: vk::Format) -> ImageDetails {
+        let format = vk::SurfaceFormatKHR {
+            format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        ImageDetails::builder(format, MOCK_WINDOW, vk::PresentModeKHR::FIFO)
+            .build(&surface_caps(MOCK_WINDOW))
+    }
+
+    #[test]
+    #[cfg(feature = "surface")]
+    fn new_checked_rejects_unsupported_surfaces() {
+        let err = new_checked_error(false, mock_details(vk::Format::B8G8R8A8_SRGB));
+        assert_eq!(
+            err,
+            crate::Error::InvalidImageDetails(InvalidDetails::UnsupportedSurface)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "surface")]
+    fn new_checked_validates_the_details() {
+        let err = new_checked_error(true, mock_details(vk::Format::R8G8B8A8_UNORM));
+        assert_eq!(
+            err,
+            crate::Error::InvalidImageDetails(InvalidDetails::Format(vk::SurfaceFormatKHR {
+                format: vk::Format::R8G8B8A8_UNORM,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            }))
+        );
     }
 }
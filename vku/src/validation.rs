@@ -0,0 +1,176 @@
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use ash::vk;
+
+/// The names of the message IDs used by the validation layers for the debug printf output,
+/// which changed across the versions of the layers
+pub const DEBUG_PRINTF_MESSAGE_IDS: [&str; 3] = [
+    "UNASSIGNED-DEBUG-PRINTF",
+    "WARNING-DEBUG-PRINTF",
+    "VVL-DEBUG-PRINTF",
+];
+
+/// The name of the extended instruction set imported by the shaders that call `debugPrintfEXT`
+pub const DEBUG_PRINTF_INSTRUCTION_SET: &str = "NonSemantic.DebugPrintf";
+
+/// Returns whether or not the SPIR-V module `spirv` imports the `NonSemantic.DebugPrintf`
+/// extended instruction set, i.e. if it was compiled with debug printf support
+///
+/// Shaders compiled without it produce no output even with debug printf enabled
+/// in the [`ValidationConfig`], [`vku::LogicalDev::create_shader_module`] warns about them.
+///
+/// Returns `false` when `spirv` is not a valid SPIR-V module.
+pub fn uses_debug_printf(spirv: &[u32]) -> bool {
+    const MAGIC: u32 = 0x0723_0203;
+    const HEADER_LEN: usize = 5;
+    const OP_EXT_INST_IMPORT: u32 = 11;
+    // The imports come before the function declarations
+    const OP_FUNCTION: u32 = 54;
+
+    let Some((&magic, _)) = spirv.split_first() else {
+        return false;
+    };
+    let swap = match magic {
+        MAGIC => false,
+        _ if magic.swap_bytes() == MAGIC => true,
+        _ => return false,
+    };
+    let word = |w: u32| if swap { w.swap_bytes() } else { w };
+
+    let mut words = spirv.get(HEADER_LEN..).unwrap_or_default();
+    while let Some(&first) = words.first() {
+        let (len, opcode) = ((word(first) >> 16) as usize, word(first) & 0xffff);
+        let Some(inst) = words.get(..len).filter(|_| len > 0) else {
+            return false;
+        };
+        match opcode {
+            OP_FUNCTION => return false,
+            // Operands: the result id and the name as a null-terminated string
+            OP_EXT_INST_IMPORT if inst.len() > 2 => {
+                let name: Vec<u8> = inst[2..]
+                    .iter()
+                    .flat_map(|&w| word(w).to_le_bytes())
+                    .take_while(|&b| b != 0)
+                    .collect();
+                if name == DEBUG_PRINTF_INSTRUCTION_SET.as_bytes() {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        words = &words[len..];
+    }
+    false
+}
+
+/// The optional features of the validation layers to enable,
+/// check [`vku::InstanceBuilder::validation`]
+///
+/// They require the `VK_EXT_validation_features` instance extension,
+/// provided by the Khronos validation layer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationConfig {
+    /// Instruments the shaders to validate the accesses that can only be checked on the GPU
+    pub gpu_assisted: bool,
+    /// Warns about API usages that are valid but potentially inefficient
+    pub best_practices: bool,
+    /// Checks for missing or wrong synchronization between commands
+    pub synchronization: bool,
+    /// Forwards the output of `debugPrintfEXT` in shaders to the debug messenger,
+    /// it can't be enabled along with `gpu_assisted`
+    pub debug_printf: bool,
+}
+
+impl ValidationConfig {
+    /// Returns the validation features to enable
+    pub fn enables(&self) -> Vec<vk::ValidationFeatureEnableEXT> {
+        use vk::ValidationFeatureEnableEXT as Enable;
+        [
+            (self.gpu_assisted, Enable::GPU_ASSISTED),
+            (self.best_practices, Enable::BEST_PRACTICES),
+            (self.synchronization, Enable::SYNCHRONIZATION_VALIDATION),
+            (self.debug_printf, Enable::DEBUG_PRINTF),
+        ]
+        .into_iter()
+        .filter_map(|(on, feature)| on.then_some(feature))
+        .collect()
+    }
+
+    /// Returns whether or not any feature is enabled
+    pub fn is_empty(&self) -> bool {
+        self.enables().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `s` as a null-terminated SPIR-V literal string
+    fn literal(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+        bytes
+            .chunks(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    }
+
+    fn inst(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        let len = operands.len() as u32 + 1;
+        std::iter::once(len << 16 | opcode)
+            .chain(operands.iter().copied())
+            .collect()
+    }
+
+    fn module(imports: &[&str]) -> Vec<u32> {
+        let mut spirv = vec![0x0723_0203, 0x0001_0000, 0, 8, 0];
+        // OpCapability Shader
+        spirv.extend(inst(17, &[1]));
+        for (id, name) in (1..).zip(imports) {
+            let operands: Vec<_> = std::iter::once(id).chain(literal(name)).collect();
+            spirv.extend(inst(11, &operands));
+        }
+        // OpMemoryModel Logical GLSL450
+        spirv.extend(inst(14, &[0, 1]));
+        spirv
+    }
+
+    #[test]
+    fn detects_the_import() {
+        assert!(uses_debug_printf(&module(&[
+            "GLSL.std.450",
+            DEBUG_PRINTF_INSTRUCTION_SET
+        ])));
+        assert!(!uses_debug_printf(&module(&["GLSL.std.450"])));
+        assert!(!uses_debug_printf(&module(&["NonSemantic.DebugPrintfX"])));
+    }
+
+    #[test]
+    fn handles_swapped_endianness() {
+        let spirv: Vec<_> = module(&[DEBUG_PRINTF_INSTRUCTION_SET])
+            .into_iter()
+            .map(u32::swap_bytes)
+            .collect();
+        assert!(uses_debug_printf(&spirv));
+    }
+
+    #[test]
+    fn rejects_invalid_modules() {
+        assert!(!uses_debug_printf(&[]));
+        let mut spirv = module(&[DEBUG_PRINTF_INSTRUCTION_SET]);
+        spirv[0] = 0;
+        assert!(!uses_debug_printf(&spirv));
+        // An instruction claiming more words than available
+        let mut spirv = module(&[]);
+        spirv.extend(inst(11, &[1]).into_iter().map(|w| w | 0xff << 16));
+        assert!(!uses_debug_printf(&spirv));
+        // A zero length instruction
+        let mut spirv = module(&[]);
+        spirv.push(0);
+        spirv.extend(inst(11, &[1]));
+        assert!(!uses_debug_printf(&spirv));
+    }
+}
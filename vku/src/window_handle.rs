@@ -0,0 +1,199 @@
+//! Support for the windows implementing the `raw-window-handle` traits
+//!
+//! `ash-window` and the rest of the crate use the raw handles of `raw-window-handle` 0.5.
+//! With the `rwh_05` feature the windows implementing its traits are used directly,
+//! with the `rwh_06` feature the newer handles are converted into the 0.5 ones.
+//! Both features can be enabled at the same time.
+
+#[allow(unused_imports)]
+use crate as vku; // <--- Used in docs
+
+use raw_window_handle as rwh_05;
+#[cfg(feature = "rwh_06")]
+use rwh_06::{HasDisplayHandle, HasWindowHandle};
+
+/// Returns the name of the window system of a `raw-window-handle` 0.6 display handle,
+/// used in the error messages
+///
+/// Same as [`vku::surface::window_system`] for the newer handles
+#[cfg(feature = "rwh_06")]
+pub fn window_system(display: rwh_06::RawDisplayHandle) -> &'static str {
+    use rwh_06::RawDisplayHandle as H;
+    match display {
+        H::UiKit(_) => "UIKit",
+        H::AppKit(_) => "AppKit",
+        H::Orbital(_) => "Orbital",
+        H::Ohos(_) => "OpenHarmony",
+        H::Xlib(_) => "Xlib",
+        H::Xcb(_) => "XCB",
+        H::Wayland(_) => "Wayland",
+        H::Drm(_) => "DRM",
+        H::Gbm(_) => "GBM",
+        H::Windows(_) => "Win32",
+        H::Web(_) => "Web",
+        H::Android(_) => "Android",
+        H::Haiku(_) => "Haiku",
+        _ => "unknown",
+    }
+}
+
+/// Converts a display handle of `raw-window-handle` 0.6 into the 0.5 one
+///
+/// Returns [`None`] for the window systems not supported by Vulkan
+#[cfg(feature = "rwh_06")]
+pub fn display_handle(display: rwh_06::RawDisplayHandle) -> Option<rwh_05::RawDisplayHandle> {
+    use rwh_05::RawDisplayHandle as Old;
+    use rwh_06::RawDisplayHandle as New;
+    Some(match display {
+        New::Windows(_) => Old::Windows(rwh_05::WindowsDisplayHandle::empty()),
+        New::AppKit(_) => Old::AppKit(rwh_05::AppKitDisplayHandle::empty()),
+        New::UiKit(_) => Old::UiKit(rwh_05::UiKitDisplayHandle::empty()),
+        New::Android(_) => Old::Android(rwh_05::AndroidDisplayHandle::empty()),
+        New::Xlib(h) => {
+            let mut old = rwh_05::XlibDisplayHandle::empty();
+            old.display = h.display.map_or(std::ptr::null_mut(), |p| p.as_ptr());
+            old.screen = h.screen;
+            Old::Xlib(old)
+        }
+        New::Xcb(h) => {
+            let mut old = rwh_05::XcbDisplayHandle::empty();
+            old.connection = h.connection.map_or(std::ptr::null_mut(), |p| p.as_ptr());
+            old.screen = h.screen;
+            Old::Xcb(old)
+        }
+        New::Wayland(h) => {
+            let mut old = rwh_05::WaylandDisplayHandle::empty();
+            old.display = h.display.as_ptr();
+            Old::Wayland(old)
+        }
+        _ => return None,
+    })
+}
+
+/// Converts a window handle of `raw-window-handle` 0.6 into the 0.5 one
+///
+/// Returns [`None`] for the window systems not supported by Vulkan
+#[cfg(feature = "rwh_06")]
+pub fn window_handle(window: rwh_06::RawWindowHandle) -> Option<rwh_05::RawWindowHandle> {
+    use rwh_05::RawWindowHandle as Old;
+    use rwh_06::RawWindowHandle as New;
+    Some(match window {
+        New::Win32(h) => {
+            let mut old = rwh_05::Win32WindowHandle::empty();
+            old.hwnd = h.hwnd.get() as *mut _;
+            old.hinstance = h.hinstance.map_or(0, |h| h.get()) as *mut _;
+            Old::Win32(old)
+        }
+        New::AppKit(h) => {
+            let mut old = rwh_05::AppKitWindowHandle::empty();
+            old.ns_view = h.ns_view.as_ptr();
+            Old::AppKit(old)
+        }
+        New::UiKit(h) => {
+            let mut old = rwh_05::UiKitWindowHandle::empty();
+            old.ui_view = h.ui_view.as_ptr();
+            old.ui_view_controller = h
+                .ui_view_controller
+                .map_or(std::ptr::null_mut(), |p| p.as_ptr());
+            Old::UiKit(old)
+        }
+        New::AndroidNdk(h) => {
+            let mut old = rwh_05::AndroidNdkWindowHandle::empty();
+            old.a_native_window = h.a_native_window.as_ptr();
+            Old::AndroidNdk(old)
+        }
+        New::Xlib(h) => {
+            let mut old = rwh_05::XlibWindowHandle::empty();
+            old.window = h.window;
+            old.visual_id = h.visual_id;
+            Old::Xlib(old)
+        }
+        New::Xcb(h) => {
+            let mut old = rwh_05::XcbWindowHandle::empty();
+            old.window = h.window.get();
+            old.visual_id = h.visual_id.map_or(0, |id| id.get());
+            Old::Xcb(old)
+        }
+        New::Wayland(h) => {
+            let mut old = rwh_05::WaylandWindowHandle::empty();
+            old.surface = h.surface.as_ptr();
+            Old::Wayland(old)
+        }
+        _ => return None,
+    })
+}
+
+/// Returns the raw display handle of `window` converted into the 0.5 one
+#[cfg(feature = "rwh_06")]
+fn raw_display<W: HasDisplayHandle + ?Sized>(
+    window: &W,
+) -> super::Result<rwh_05::RawDisplayHandle> {
+    let display = window
+        .display_handle()
+        .map_err(|_| super::Error::WindowHandleUnavailable)?
+        .as_raw();
+    display_handle(display).ok_or(super::Error::UnsupportedWindowSystem(window_system(
+        display,
+    )))
+}
+
+/// Same as [`vku::surface::extensions_checked`] but takes a window
+/// implementing the `raw-window-handle` 0.6 traits
+#[cfg(feature = "rwh_06")]
+pub fn extensions_checked<W: HasDisplayHandle + ?Sized>(
+    window: &W,
+    entry: &ash::Entry,
+) -> super::Result<super::ExtensionList> {
+    super::surface::extensions_checked(raw_display(window)?, entry)
+}
+
+/// Same as [`vku::Surface::new`] but takes a window implementing
+/// the `raw-window-handle` 0.6 traits
+///
+/// The surface borrows the window, so that it can't be dropped before the surface
+#[cfg(feature = "rwh_06")]
+pub fn create_surface<'a, I, W>(instance: I, window: &'a W) -> super::Result<super::Surface<'a, I>>
+where
+    I: super::InstanceHolder,
+    W: HasDisplayHandle + HasWindowHandle + ?Sized,
+{
+    let display = raw_display(window)?;
+    let raw = window
+        .window_handle()
+        .map_err(|_| super::Error::WindowHandleUnavailable)?
+        .as_raw();
+    let raw = window_handle(raw).ok_or(super::Error::UnsupportedWindowSystem(
+        super::surface::window_system(display),
+    ))?;
+    super::Surface::new(instance, display, raw)
+}
+
+/// Same as [`vku::surface::extensions_checked`] but takes a window
+/// implementing the `raw-window-handle` 0.5 traits
+#[cfg(feature = "rwh_05")]
+pub fn extensions_checked_rwh05<W: rwh_05::HasRawDisplayHandle + ?Sized>(
+    window: &W,
+    entry: &ash::Entry,
+) -> super::Result<super::ExtensionList> {
+    super::surface::extensions_checked(window.raw_display_handle(), entry)
+}
+
+/// Same as [`vku::Surface::new`] but takes a window implementing
+/// the `raw-window-handle` 0.5 traits
+///
+/// The surface borrows the window, so that it can't be dropped before the surface
+#[cfg(feature = "rwh_05")]
+pub fn create_surface_rwh05<'a, I, W>(
+    instance: I,
+    window: &'a W,
+) -> super::Result<super::Surface<'a, I>>
+where
+    I: super::InstanceHolder,
+    W: rwh_05::HasRawDisplayHandle + rwh_05::HasRawWindowHandle + ?Sized,
+{
+    super::Surface::new(
+        instance,
+        window.raw_display_handle(),
+        window.raw_window_handle(),
+    )
+}
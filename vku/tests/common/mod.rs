@@ -0,0 +1,74 @@
+//! Helpers shared by the tests that need a Vulkan implementation
+//!
+//! Those tests are skipped, returning early, when the Vulkan loader or a driver (ICD)
+//! is not installed, e.g. on CI machines without a GPU or a software implementation
+
+#![allow(dead_code)]
+
+use std::ffi::CStr;
+
+/// The application name used by the tests
+pub const APP_NAME: &CStr = c"vku tests";
+
+/// Loads the Vulkan loader, returns [`None`] if it's not installed
+pub fn entry() -> Option<ash::Entry> {
+    match unsafe { ash::Entry::load() } {
+        Ok(entry) => Some(entry),
+        Err(err) => {
+            eprintln!("skipping: the Vulkan loader is not available ({err})");
+            None
+        }
+    }
+}
+
+/// Lists the physical devices, returns [`None`] if there are none
+pub fn devices<I: vku::InstanceHolder>(instance: I) -> Option<vku::PhysicalDevList<I>> {
+    match vku::PhysicalDevList::list(instance) {
+        Ok(list) => Some(list),
+        Err(err @ vku::Error::NoVulkanDevices { .. }) => {
+            eprintln!("skipping: {err}");
+            None
+        }
+        Err(err) => panic!("failed to list the physical devices: {err}"),
+    }
+}
+
+/// The name of the Khronos validation layer
+pub const VALIDATION_LAYER: &CStr = c"VK_LAYER_KHRONOS_validation";
+
+/// Returns whether the Khronos validation layer is installed along with
+/// the `VK_EXT_validation_features` extension it provides
+pub fn validation_layer(entry: &ash::Entry) -> bool {
+    let features = ash::vk::ExtValidationFeaturesFn::name();
+    let available = vku::instance::available_extensions(entry, &[VALIDATION_LAYER])
+        .unwrap_or_default()
+        .iter()
+        .any(|prop| unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) } == features);
+    if !available {
+        eprintln!("skipping: the validation layer or {features:?} is not available");
+    }
+    available
+}
+
+/// Records the message IDs reported to a debug messenger
+///
+/// The user data of the messenger must point to a [`std::sync::Mutex<Vec<String>>`]
+/// that outlives it
+pub unsafe extern "system" fn record_message_ids(
+    _severity: ash::vk::DebugUtilsMessageSeverityFlagsEXT,
+    _ty: ash::vk::DebugUtilsMessageTypeFlagsEXT,
+    data: *const ash::vk::DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut std::ffi::c_void,
+) -> ash::vk::Bool32 {
+    let ids = &*(user_data as *const std::sync::Mutex<Vec<String>>);
+    if let Some(data) = data
+        .as_ref()
+        .filter(|data| !data.p_message_id_name.is_null())
+    {
+        let id = CStr::from_ptr(data.p_message_id_name).to_string_lossy();
+        if let Ok(mut ids) = ids.lock() {
+            ids.push(id.into_owned());
+        }
+    }
+    ash::vk::FALSE
+}
@@ -0,0 +1,180 @@
+//! Checks that the output of `debugPrintfEXT` in a compute shader reaches the printf sink
+
+#![cfg(feature = "debug-utils")]
+
+mod common;
+
+use std::{
+    ffi::CStr,
+    sync::{Arc, Mutex},
+};
+
+use ash::vk;
+
+/// The message printed by [`shader`]
+const MESSAGE: &str = "vku printf 42";
+
+/// Encodes `s` as a null-terminated SPIR-V literal string
+fn literal(s: &str) -> Vec<u32> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+    bytes
+        .chunks(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+fn inst(opcode: u32, operands: &[u32]) -> Vec<u32> {
+    let len = operands.len() as u32 + 1;
+    std::iter::once(len << 16 | opcode)
+        .chain(operands.iter().copied())
+        .collect()
+}
+
+/// Assembles a compute shader that calls `debugPrintfEXT("vku printf %u", 42)`
+fn shader() -> Vec<u32> {
+    let (import, main, format, void, void_fn, uint, value, label, result) =
+        (1, 2, 3, 4, 5, 6, 7, 8, 9);
+    let with_literal =
+        |head: &[u32], s: &str| -> Vec<u32> { head.iter().copied().chain(literal(s)).collect() };
+    let mut spirv = vec![0x0723_0203, 0x0001_0000, 0, 10, 0];
+    // OpCapability Shader
+    spirv.extend(inst(17, &[1]));
+    spirv.extend(inst(10, &literal("SPV_KHR_non_semantic_info")));
+    spirv.extend(inst(
+        11,
+        &with_literal(&[import], "NonSemantic.DebugPrintf"),
+    ));
+    // OpMemoryModel Logical GLSL450
+    spirv.extend(inst(14, &[0, 1]));
+    // OpEntryPoint GLCompute
+    spirv.extend(inst(15, &with_literal(&[5, main], "main")));
+    // OpExecutionMode LocalSize 1 1 1
+    spirv.extend(inst(16, &[main, 17, 1, 1, 1]));
+    spirv.extend(inst(7, &with_literal(&[format], "vku printf %u")));
+    spirv.extend(inst(19, &[void]));
+    spirv.extend(inst(33, &[void_fn, void]));
+    spirv.extend(inst(21, &[uint, 32, 0]));
+    spirv.extend(inst(43, &[uint, value, 42]));
+    spirv.extend(inst(54, &[void, main, 0, void_fn]));
+    spirv.extend(inst(248, &[label]));
+    // OpExtInst DebugPrintf
+    spirv.extend(inst(12, &[void, result, import, 1, format, value]));
+    spirv.extend(inst(253, &[]));
+    spirv.extend(inst(56, &[]));
+    spirv
+}
+
+#[test]
+fn compute_printf_reaches_the_sink() {
+    let Some(entry) = common::entry() else { return };
+    if !common::validation_layer(&entry) {
+        return;
+    }
+    let validation = vku::ValidationConfig {
+        debug_printf: true,
+        ..Default::default()
+    };
+    let instance = unsafe {
+        vku::Instance::builder(&entry, common::APP_NAME)
+            .layers([common::VALIDATION_LAYER])
+            .extensions([vk::ExtDebugUtilsFn::name()])
+            .validation(validation)
+            .build()
+    }
+    .unwrap();
+    assert_eq!(instance.info().validation, validation);
+
+    let lines = Arc::new(Mutex::new(Vec::<String>::new()));
+    let sink_lines = lines.clone();
+    let sink = Box::new(move |msg: &str| sink_lines.lock().unwrap().push(msg.to_owned()));
+    let debug_utils = vku::DebugUtils::with_printf_sink(instance, sink).unwrap();
+    let Some(devices) = common::devices(debug_utils) else {
+        return;
+    };
+
+    // Non semantic instructions are core since Vulkan 1.3
+    let non_semantic: &CStr = c"VK_KHR_shader_non_semantic_info";
+    let selected = devices.iter().enumerate().find_map(|(index, dev)| {
+        let family = dev
+            .queue_families()
+            .iter()
+            .position(|family| family.queue_flags.contains(vk::QueueFlags::COMPUTE))?;
+        let extension = dev.supports_extension(non_semantic).unwrap();
+        (extension || dev.properties().api_version >= vk::API_VERSION_1_3).then_some((
+            index,
+            family as u32,
+            extension,
+        ))
+    });
+    let Some((index, family, extension)) = selected else {
+        eprintln!("skipping: no device supports non semantic instructions in compute shaders");
+        return;
+    };
+    let extensions: vku::ExtensionList = extension.then_some(non_semantic).into_iter().collect();
+    let queues = vec![vku::QueueFamilyInfo {
+        index: family,
+        priorities: vec![1.0],
+    }];
+    let logical_dev = unsafe { devices.select_checked(index, queues, extensions) }.unwrap();
+    let queue = *logical_dev.queue_with(vk::QueueFlags::COMPUTE).unwrap();
+    let device = logical_dev.vk_device();
+
+    let spirv = shader();
+    assert!(vku::validation::uses_debug_printf(&spirv));
+    unsafe {
+        let module = logical_dev.create_shader_module(&spirv).unwrap();
+        let layout = device
+            .create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default(), None)
+            .unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(module)
+            .name(c"main");
+        let pipeline_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(stage.build())
+            .layout(layout);
+        let pipeline = device
+            .create_compute_pipelines(vk::PipelineCache::null(), &[pipeline_info.build()], None)
+            .map_err(|(_, err)| err)
+            .unwrap()[0];
+
+        let pool = logical_dev
+            .create_command_pool(&queue, vk::CommandPoolCreateFlags::empty())
+            .unwrap();
+        let alloc_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let cmd = device.allocate_command_buffers(&alloc_info).unwrap()[0];
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        device.begin_command_buffer(cmd, &begin_info).unwrap();
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+        device.cmd_dispatch(cmd, 1, 1, 1);
+        device.end_command_buffer(cmd).unwrap();
+
+        let cmds = [cmd];
+        let submit = vk::SubmitInfo::builder().command_buffers(&cmds);
+        logical_dev
+            .submit(
+                &queue,
+                vk::QueueFlags::COMPUTE,
+                &[submit.build()],
+                vk::Fence::null(),
+            )
+            .unwrap();
+        device.queue_wait_idle(queue.handle).unwrap();
+
+        device.destroy_command_pool(pool, None);
+        device.destroy_pipeline(pipeline, None);
+        device.destroy_pipeline_layout(layout, None);
+        device.destroy_shader_module(module, None);
+    }
+
+    let lines = lines.lock().unwrap();
+    assert!(
+        lines.iter().any(|line| line.contains(MESSAGE)),
+        "{MESSAGE:?} not printed: {lines:?}"
+    );
+}
@@ -0,0 +1,123 @@
+//! Checks that the public API matches the enabled cargo features,
+//! run with `--no-default-features` and the other combinations to cover all of them
+
+use ash::vk;
+
+/// The items that don't depend on any feature
+#[test]
+fn headless_api() {
+    let criteria = vku::DeviceCriteria::default();
+    assert!(criteria
+        .rank(vk::PhysicalDeviceType::DISCRETE_GPU)
+        .is_some());
+    let _ = vku::ExtensionList::from([vk::KhrSwapchainFn::name()]);
+    let _ = vku::swapchain::ImageCountPolicy::Minimum;
+}
+
+/// The error variants must not depend on the features, so that the matches naming them
+/// keep compiling when a feature is enabled anywhere in the dependency graph
+#[test]
+fn error_variants_are_additive() {
+    let err = vku::Error::from(vku::swapchain::InvalidDetails::UnsupportedSurface);
+    let described = match err {
+        vku::Error::Vulkan(_) => "vulkan",
+        vku::Error::UnsupportedWindowSystem(_) => "window system",
+        vku::Error::WindowHandleUnavailable => "window handle",
+        vku::Error::MissingInstanceExtensions { .. } => "instance extensions",
+        vku::Error::InvalidImageDetails(_) => "image details",
+        vku::Error::InvalidQueueInfos { .. } => "queue infos",
+        vku::Error::DeviceGroupsUnsupported => "device groups",
+        vku::Error::NoVulkanDevices { .. } => "no devices",
+        vku::Error::ForcedDeviceRejected { .. } => "forced device",
+        vku::Error::DeviceIndexOutOfRange { .. } => "device index",
+        vku::Error::Context { .. } => "context",
+        // The enum is non exhaustive
+        _ => "other",
+    };
+    assert_eq!(described, "image details");
+}
+
+#[cfg(feature = "surface")]
+#[test]
+fn surface_api() {
+    let display =
+        raw_window_handle::RawDisplayHandle::Xlib(raw_window_handle::XlibDisplayHandle::empty());
+    assert_eq!(vku::surface::window_system(display), "Xlib");
+}
+
+#[cfg(feature = "debug-utils")]
+#[test]
+fn debug_utils_api() {
+    let info = vku::debug_utils::create_info();
+    assert!(info.pfn_user_callback.is_some());
+}
+
+/// An entry whose functions are never loaded, enough for the calls that fail early
+#[cfg(any(feature = "rwh_05", feature = "rwh_06"))]
+fn unloaded_entry() -> ash::Entry {
+    unsafe extern "system" fn get_instance_proc_addr(
+        _instance: vk::Instance,
+        _name: *const std::os::raw::c_char,
+    ) -> vk::PFN_vkVoidFunction {
+        None
+    }
+    unsafe {
+        ash::Entry::from_static_fn(vk::StaticFn {
+            get_instance_proc_addr,
+        })
+    }
+}
+
+#[cfg(feature = "rwh_05")]
+#[test]
+fn rwh_05_api() {
+    use raw_window_handle::{
+        HasRawDisplayHandle, HasRawWindowHandle, OrbitalDisplayHandle, OrbitalWindowHandle,
+        RawDisplayHandle, RawWindowHandle,
+    };
+
+    struct Window;
+    unsafe impl HasRawDisplayHandle for Window {
+        fn raw_display_handle(&self) -> RawDisplayHandle {
+            RawDisplayHandle::Orbital(OrbitalDisplayHandle::empty())
+        }
+    }
+    unsafe impl HasRawWindowHandle for Window {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            RawWindowHandle::Orbital(OrbitalWindowHandle::empty())
+        }
+    }
+
+    let _ = vku::Surface::<vku::Instance>::from_window_rwh05::<Window>;
+    let entry = unloaded_entry();
+    match vku::window_handle::extensions_checked_rwh05(&Window, &entry) {
+        Err(vku::Error::UnsupportedWindowSystem(name)) => assert_eq!(name, "Orbital"),
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+#[test]
+fn rwh_06_api() {
+    use rwh_06::{DisplayHandle, HandleError, HasDisplayHandle, RawDisplayHandle};
+
+    struct Window(RawDisplayHandle);
+    impl HasDisplayHandle for Window {
+        fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+            Ok(unsafe { DisplayHandle::borrow_raw(self.0) })
+        }
+    }
+
+    let display = RawDisplayHandle::Ohos(rwh_06::OhosDisplayHandle::new());
+    assert_eq!(vku::window_handle::display_handle(display), None);
+    assert_eq!(vku::window_handle::window_system(display), "OpenHarmony");
+
+    let entry = unloaded_entry();
+    match vku::window_handle::extensions_checked(&Window(display), &entry) {
+        Err(vku::Error::UnsupportedWindowSystem(name)) => assert_eq!(name, "OpenHarmony"),
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[cfg(all(any(feature = "rwh_05", feature = "rwh_06"), not(feature = "surface")))]
+compile_error!("the rwh_05 and rwh_06 features must enable the surface feature");
@@ -0,0 +1,58 @@
+//! Checks the ownership of the handles adopted with the `from_raw` constructors
+
+mod common;
+
+use std::{ffi::CStr, sync::Arc};
+
+use vku::{allocator::AllocationStats, RawHandle};
+
+const NO_NAMES: [&CStr; 0] = [];
+
+#[test]
+fn borrowed_instance_is_not_destroyed() {
+    let Some(entry) = common::entry() else { return };
+    let allocator = Arc::new(vku::CountingAllocator::new());
+    let instance = vku::Instance::with_allocator(
+        &entry,
+        NO_NAMES,
+        NO_NAMES,
+        common::APP_NAME,
+        allocator.clone(),
+    )
+    .unwrap();
+
+    let info = instance.info().clone();
+    let borrowed = unsafe {
+        vku::Instance::from_raw(&entry, instance.raw(), info, false, Some(allocator.clone()))
+    };
+    assert!(!borrowed.is_owned());
+    drop(borrowed);
+
+    // The instance is still alive and usable through its owner
+    unsafe { instance.vk_instance().enumerate_physical_devices() }.unwrap();
+    drop(instance);
+    assert_eq!(allocator.total(), AllocationStats::default());
+}
+
+#[test]
+fn adopted_instance_is_destroyed_with_its_allocator() {
+    let Some(entry) = common::entry() else { return };
+    let allocator = Arc::new(vku::CountingAllocator::new());
+    let instance = vku::Instance::with_allocator(
+        &entry,
+        NO_NAMES,
+        NO_NAMES,
+        common::APP_NAME,
+        allocator.clone(),
+    )
+    .unwrap();
+
+    // Hand the instance over to a new owner, the original one must not destroy it
+    let (handle, info) = (instance.raw(), instance.info().clone());
+    let adopted =
+        unsafe { vku::Instance::from_raw(&entry, handle, info, true, Some(allocator.clone())) };
+    std::mem::forget(instance);
+    assert!(adopted.is_owned());
+    drop(adopted);
+    assert_eq!(allocator.total(), AllocationStats::default());
+}
@@ -0,0 +1,82 @@
+//! Checks the device selection against the Vulkan implementation installed on the machine
+
+mod common;
+
+use std::ffi::CStr;
+
+const NO_NAMES: [&CStr; 0] = [];
+
+#[test]
+fn forced_index_out_of_range() {
+    let Some(entry) = common::entry() else { return };
+    let instance = vku::Instance::new(&entry, NO_NAMES, NO_NAMES, common::APP_NAME).unwrap();
+    let Some(devices) = common::devices(instance) else {
+        return;
+    };
+
+    let count = devices.iter().count();
+    let criteria = vku::DeviceCriteria::default().force_index(Some(count));
+    match devices.select_best(&criteria, |_| true) {
+        Err(vku::Error::ForcedDeviceRejected { index, .. }) => assert_eq!(index, count),
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn forced_index_unsuitable() {
+    let Some(entry) = common::entry() else { return };
+    let instance = vku::Instance::new(&entry, NO_NAMES, NO_NAMES, common::APP_NAME).unwrap();
+    let Some(devices) = common::devices(instance) else {
+        return;
+    };
+
+    let criteria = vku::DeviceCriteria::default().force_index(Some(0));
+    assert!(matches!(
+        devices.select_best(&criteria, |_| false),
+        Err(vku::Error::ForcedDeviceRejected { index: 0, .. })
+    ));
+    assert_eq!(devices.select_best(&criteria, |_| true), Ok(Some(0)));
+}
+
+#[test]
+fn software_devices_need_to_be_allowed() {
+    let Some(entry) = common::entry() else { return };
+    let instance = vku::Instance::new(&entry, NO_NAMES, NO_NAMES, common::APP_NAME).unwrap();
+    let Some(devices) = common::devices(instance) else {
+        return;
+    };
+
+    let software_only =
+        vku::DeviceCriteria::default().device_types(vec![ash::vk::PhysicalDeviceType::CPU]);
+    assert_eq!(devices.select_best(&software_only, |_| true), Ok(None));
+
+    let has_software = devices
+        .iter()
+        .any(|dev| dev.properties().device_type == ash::vk::PhysicalDeviceType::CPU);
+    let allowed = software_only.allow_software(true);
+    let selected = devices.select_best(&allowed, |_| true).unwrap();
+    assert_eq!(selected.is_some(), has_software);
+}
+
+#[test]
+fn checked_selection_out_of_range() {
+    let Some(entry) = common::entry() else { return };
+    let instance = vku::Instance::new(&entry, NO_NAMES, NO_NAMES, common::APP_NAME).unwrap();
+    let Some(devices) = common::devices(instance) else {
+        return;
+    };
+
+    let count = devices.iter().count();
+    let queues = vec![vku::QueueFamilyInfo {
+        index: 0,
+        priorities: vec![1.0],
+    }];
+    let result = unsafe { devices.select_checked(count, queues, NO_NAMES) };
+    match result {
+        Err(vku::Error::DeviceIndexOutOfRange { index, count: c }) => {
+            assert_eq!((index, c), (count, count))
+        }
+        Err(err) => panic!("unexpected error {err}"),
+        Ok(_) => panic!("a device has been created for an out of range index"),
+    }
+}
@@ -0,0 +1,56 @@
+//! Checks that the wrappers release their handles exactly once when a panic happens
+//! between construction steps, using the [`vku::CountingAllocator`] to detect leaks
+
+mod common;
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use vku::allocator::AllocationStats;
+
+/// Runs `steps` with an allocator used by the instance and all its children,
+/// expecting it to panic, then checks that all the allocations have been freed
+///
+/// `steps` returns [`None`] to skip the test when the implementation is missing
+fn assert_released_on_panic(steps: impl FnOnce(Arc<vku::CountingAllocator>) -> Option<()>) {
+    let allocator = Arc::new(vku::CountingAllocator::new());
+    let result = panic::catch_unwind(AssertUnwindSafe(|| steps(allocator.clone())));
+    match result {
+        Ok(None) => return,
+        Ok(Some(())) => panic!("the construction steps didn't panic"),
+        Err(_) => {}
+    }
+    assert_eq!(allocator.total(), AllocationStats::default());
+}
+
+#[test]
+fn panic_after_instance() {
+    assert_released_on_panic(|allocator| {
+        let entry = common::entry()?;
+        let no_names: [&std::ffi::CStr; 0] = [];
+        let _instance =
+            vku::Instance::with_allocator(&entry, no_names, no_names, common::APP_NAME, allocator)
+                .unwrap();
+        panic!("between the instance and the device creation");
+    });
+}
+
+#[test]
+fn panic_after_device() {
+    assert_released_on_panic(|allocator| {
+        let entry = common::entry()?;
+        let no_names: [&std::ffi::CStr; 0] = [];
+        let instance =
+            vku::Instance::with_allocator(&entry, no_names, no_names, common::APP_NAME, allocator)
+                .unwrap();
+        let devices = common::devices(instance)?;
+        let queue = vku::QueueFamilyInfo {
+            index: 0,
+            priorities: vec![1.0],
+        };
+        let _device = unsafe { devices.select_checked(0, vec![queue], no_names) }.unwrap();
+        panic!("between the device and the swapchain creation");
+    });
+}
@@ -0,0 +1,93 @@
+//! Checks the validation features chained to the instance with [`vku::InstanceBuilder::push_next`]
+
+mod common;
+
+use std::sync::Mutex;
+
+use ash::{extensions::ext, vk};
+
+/// Creates an instance with the validation layer, chaining `enables` when not empty,
+/// then creates a device without retrieving the physical device features first,
+/// which the best practices validation reports.
+///
+/// Returns the message IDs reported to a debug messenger meanwhile,
+/// or [`None`] if there are no physical devices
+fn message_ids(
+    entry: &ash::Entry,
+    enables: &[vk::ValidationFeatureEnableEXT],
+) -> Option<Vec<String>> {
+    let mut features = vk::ValidationFeaturesEXT::builder().enabled_validation_features(enables);
+    let mut builder = vku::Instance::builder(entry, common::APP_NAME)
+        .layers([common::VALIDATION_LAYER])
+        .extensions([
+            vk::ExtValidationFeaturesFn::name(),
+            vk::ExtDebugUtilsFn::name(),
+        ]);
+    if !enables.is_empty() {
+        builder = builder.push_next(&mut features);
+    }
+    let instance = unsafe { builder.build() }.unwrap();
+
+    let ids = Mutex::new(Vec::new());
+    let messenger_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+        .message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING)
+        .message_type(
+            vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+        )
+        .pfn_user_callback(Some(common::record_message_ids))
+        .user_data(&ids as *const Mutex<Vec<String>> as *mut _);
+    let debug_utils = ext::DebugUtils::new(entry, instance.vk_instance());
+    let messenger =
+        unsafe { debug_utils.create_debug_utils_messenger(&messenger_info, None) }.unwrap();
+
+    let physical_devices = unsafe { instance.vk_instance().enumerate_physical_devices() }.unwrap();
+    let Some(&physical_device) = physical_devices.first() else {
+        eprintln!("skipping: there are no physical devices");
+        unsafe { debug_utils.destroy_debug_utils_messenger(messenger, None) };
+        return None;
+    };
+    // Every device has at least a queue in its first family
+    let queue_info = [vk::DeviceQueueCreateInfo::builder()
+        .queue_family_index(0)
+        .queue_priorities(&[1.0])
+        .build()];
+    let device_info = vk::DeviceCreateInfo::builder().queue_create_infos(&queue_info);
+    unsafe {
+        let device = instance
+            .vk_instance()
+            .create_device(physical_device, &device_info, None)
+            .unwrap();
+        device.destroy_device(None);
+    }
+
+    unsafe { debug_utils.destroy_debug_utils_messenger(messenger, None) };
+    drop(instance);
+    Some(ids.into_inner().unwrap())
+}
+
+#[test]
+fn chained_best_practices_are_reported() {
+    let Some(entry) = common::entry() else { return };
+    if !common::validation_layer(&entry) {
+        return;
+    }
+
+    // The IDs of the best practices messages change between the layer versions,
+    // but they all share this prefix, optionally preceded by `UNASSIGNED-`
+    let is_best_practices = |id: &String| id.contains("BestPractices");
+
+    let Some(baseline) = message_ids(&entry, &[]) else {
+        return;
+    };
+    assert!(
+        !baseline.iter().any(is_best_practices),
+        "best practices reported while disabled: {baseline:?}"
+    );
+
+    let enabled = message_ids(&entry, &[vk::ValidationFeatureEnableEXT::BEST_PRACTICES]).unwrap();
+    assert!(
+        enabled.iter().any(is_best_practices),
+        "best practices not reported while enabled: {enabled:?}"
+    );
+}